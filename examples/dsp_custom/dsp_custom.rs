@@ -55,22 +55,52 @@ fn get_key() -> char {
     }
 }
 
-fn my_DSP_callback(dsp_state: &rfmod::DspState, inbuffer: &mut [f32], outbuffer: &mut [f32], length: u32, in_channels: i32,
-    out_channels: i32) -> rfmod::Status {
-    for it in 0usize..(inbuffer.len() - 1usize) {
-        outbuffer[it] = inbuffer[it] * 0.2f32;
+/// This DSP filter lowers the volume, the gain being kept in the processor itself.
+struct Attenuator {
+    gain: f32,
+}
+
+impl rfmod::DspProcessor for Attenuator {
+    fn process(&mut self, input: &[f32], output: &mut [f32], frames: usize, in_channels: usize,
+               out_channels: usize) {
+        for count in 0..frames {
+            for count2 in 0..out_channels {
+                output[count * out_channels + count2] = if count2 < in_channels {
+                    input[count * in_channels + count2] * self.gain
+                } else {
+                    0f32
+                };
+            }
+        }
     }
-    for count in 0..length {
-        for count2 in 0..out_channels {
-            /* 
-                This DSP filter just halves the volume!
-                Input is modified, and sent to output.
-            */
-            outbuffer[((count as i32 * out_channels) + count2) as usize] = inbuffer[((count as i32 * in_channels) + count2) as usize] * 0.2f32;
+
+    fn set_parameter(&mut self, index: i32, value: f32) -> rfmod::Status {
+        match index {
+            0 => {
+                self.gain = value;
+                rfmod::Status::Ok
+            }
+            _ => rfmod::Status::InvalidParam
         }
-    } 
+    }
 
-    rfmod::Status::Ok
+    fn get_parameter(&self, index: i32) -> f32 {
+        self.gain
+    }
+
+    fn get_name(&self) -> String {
+        "test".to_owned()
+    }
+
+    fn get_parameters(&self) -> Vec<rfmod::DspParameterDesc> {
+        vec![rfmod::DspParameterDesc {
+            min: 0f32,
+            max: 1f32,
+            default_val: 0.2f32,
+            name: "Gain".to_owned(),
+            .. Default::default()
+        }]
+    }
 }
 
 fn main() {
@@ -122,15 +152,10 @@ fn main() {
         }
     };
 
-    let mut description : rfmod::DspDescription = Default::default();
-
-    description.read = Some(my_DSP_callback as fn(&_, &mut _, &mut _, _, _, _) -> _);
-    description.name = "test".to_owned();
-
-    let dsp = match fmod.create_DSP_with_description(&mut description) {
+    let dsp = match fmod.create_dsp_from_processor(Box::new(Attenuator { gain: 0.2f32 })) {
         Ok(dsp) => dsp,
         Err(e) => {
-            panic!("FmodSys.create_DSP_with_description failed : {:?}", e);
+            panic!("FmodSys.create_dsp_from_processor failed : {:?}", e);
         }
     };
 
@@ -0,0 +1,232 @@
+/*
+* Rust-FMOD - Copyright (c) 2018 Gomez Guillaume.
+*
+* The Original software, FmodEx library, is provided by FIRELIGHT TECHNOLOGIES.
+*
+* This software is provided 'as-is', without any express or implied warranty.
+* In no event will the authors be held liable for any damages arising from
+* the use of this software.
+*
+* Permission is granted to anyone to use this software for any purpose,
+* including commercial applications, and to alter it and redistribute it
+* freely, subject to the following restrictions:
+*
+* 1. The origin of this software must not be misrepresented; you must not claim
+*    that you wrote the original software. If you use this software in a product,
+*    an acknowledgment in the product documentation would be appreciated but is
+*    not required.
+*
+* 2. Altered source versions must be plainly marked as such, and must not be
+*    misrepresented as being the original software.
+*
+* 3. This notice may not be removed or altered from any source distribution.
+*/
+
+use ffi;
+use channel::Channel;
+use sound::Sound;
+use vector;
+use voice_manager::distance_squared;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, PartialEq, Debug, Copy)]
+/// What an [`AudiblePolicy`](trait.AudiblePolicy.html) decided for a sound about to start.
+pub enum AudibleDecision {
+    /// The sound starts.
+    Play,
+    /// The channel at the given index of the playing channels is stopped, then the sound starts.
+    Steal(usize),
+    /// The sound doesn't start.
+    Reject,
+}
+
+/// Decides whether a sound of a [`SoundGroup`](struct.SoundGroup.html) may start, used with
+/// [`SoundGroup::play_with_policy`](struct.SoundGroup.html#method.play_with_policy).
+pub trait AudiblePolicy {
+    /// Called before `sound` starts. `playing` contains the channels currently playing sounds of
+    /// the group and `max_audible` is `None` if the group has no limit.
+    fn evaluate(&mut self, sound: &Sound, playing: &[Channel],
+                max_audible: Option<usize>) -> AudibleDecision;
+
+    /// Called once the sound started.
+    fn on_started(&mut self, _sound: &Sound, _channel: &Channel) {}
+}
+
+fn is_full(playing: &[Channel], max_audible: Option<usize>) -> bool {
+    match max_audible {
+        Some(max) => playing.len() >= max,
+        None => false
+    }
+}
+
+fn steal(victim: Option<usize>) -> AudibleDecision {
+    match victim {
+        Some(pos) => AudibleDecision::Steal(pos),
+        None => AudibleDecision::Reject
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Copy, Default)]
+/// Stops the channel the farthest from the first listener when the group is full.
+pub struct StealFarthest;
+
+impl AudiblePolicy for StealFarthest {
+    fn evaluate(&mut self, sound: &Sound, playing: &[Channel],
+                max_audible: Option<usize>) -> AudibleDecision {
+        if !is_full(playing, max_audible) {
+            return AudibleDecision::Play;
+        }
+        let listener = match sound.get_system_object() {
+            Ok(s) => match s.get_3D_listener_attributes(0) {
+                Ok((pos, _, _, _)) => pos,
+                Err(_) => vector::Vector::new()
+            },
+            Err(_) => vector::Vector::new()
+        };
+        let mut victim = None;
+        let mut farthest = -1f32;
+
+        for (pos, channel) in playing.iter().enumerate() {
+            let distance = match channel.get_3D_attributes() {
+                Ok((p, _)) => distance_squared(&p, &listener),
+                Err(_) => f32::MAX
+            };
+
+            if distance > farthest {
+                farthest = distance;
+                victim = Some(pos);
+            }
+        }
+        steal(victim)
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Copy, Default)]
+/// Stops the channel with the lowest
+/// [`Channel::get_audibility`](struct.Channel.html#method.get_audibility) when the group is full.
+pub struct StealQuietest;
+
+impl AudiblePolicy for StealQuietest {
+    fn evaluate(&mut self, _sound: &Sound, playing: &[Channel],
+                max_audible: Option<usize>) -> AudibleDecision {
+        if !is_full(playing, max_audible) {
+            return AudibleDecision::Play;
+        }
+        let mut victim = None;
+        let mut quietest = f32::MAX;
+
+        for (pos, channel) in playing.iter().enumerate() {
+            let audibility = channel.get_audibility().unwrap_or(0f32);
+
+            if audibility < quietest {
+                quietest = audibility;
+                victim = Some(pos);
+            }
+        }
+        steal(victim)
+    }
+}
+
+/// Stops the channel which started first when the group is full.
+///
+/// Channels which were not started through this policy are considered older than the others.
+#[derive(Default)]
+pub struct StealOldest {
+    started: HashMap<usize, u64>,
+    clock: u64,
+}
+
+impl StealOldest {
+    pub fn new() -> StealOldest {
+        Default::default()
+    }
+}
+
+impl AudiblePolicy for StealOldest {
+    fn evaluate(&mut self, _sound: &Sound, playing: &[Channel],
+                max_audible: Option<usize>) -> AudibleDecision {
+        let keys : Vec<usize> = playing.iter().map(|c| ffi::FFI::unwrap(c) as usize).collect();
+
+        // forget the channels which stopped
+        self.started.retain(|k, _| keys.contains(k));
+        if !is_full(playing, max_audible) {
+            return AudibleDecision::Play;
+        }
+        let mut victim = None;
+        let mut oldest = u64::MAX;
+
+        for (pos, key) in keys.iter().enumerate() {
+            let started = self.started.get(key).cloned().unwrap_or(0);
+
+            if started < oldest {
+                oldest = started;
+                victim = Some(pos);
+            }
+        }
+        steal(victim)
+    }
+
+    fn on_started(&mut self, _sound: &Sound, channel: &Channel) {
+        self.clock += 1;
+        self.started.insert(ffi::FFI::unwrap(channel) as usize, self.clock);
+    }
+}
+
+/// Refuses a sound started again before its cooldown elapsed, then defers to another policy.
+///
+/// ```ignore
+/// let mut policy = rfmod::Cooldown::new(Duration::from_millis(80), rfmod::StealOldest::new());
+///
+/// footsteps.play_with_policy(&step_sound, &mut policy);
+/// ```
+pub struct Cooldown<P> {
+    cooldown: Duration,
+    overrides: HashMap<usize, Duration>,
+    last_started: HashMap<usize, Instant>,
+    policy: P,
+}
+
+impl<P: AudiblePolicy> Cooldown<P> {
+    pub fn new(cooldown: Duration, policy: P) -> Cooldown<P> {
+        Cooldown {
+            cooldown: cooldown,
+            overrides: HashMap::new(),
+            last_started: HashMap::new(),
+            policy: policy,
+        }
+    }
+
+    /// Uses a different cooldown for the given sound.
+    pub fn set_sound_cooldown(&mut self, sound: &Sound, cooldown: Duration) {
+        self.overrides.insert(ffi::FFI::unwrap(sound) as usize, cooldown);
+    }
+
+    pub fn get_policy(&self) -> &P {
+        &self.policy
+    }
+
+    pub fn get_policy_mut(&mut self) -> &mut P {
+        &mut self.policy
+    }
+}
+
+impl<P: AudiblePolicy> AudiblePolicy for Cooldown<P> {
+    fn evaluate(&mut self, sound: &Sound, playing: &[Channel],
+                max_audible: Option<usize>) -> AudibleDecision {
+        let key = ffi::FFI::unwrap(sound) as usize;
+        let cooldown = self.overrides.get(&key).cloned().unwrap_or(self.cooldown);
+
+        if let Some(last) = self.last_started.get(&key) {
+            if last.elapsed() < cooldown {
+                return AudibleDecision::Reject;
+            }
+        }
+        self.policy.evaluate(sound, playing, max_audible)
+    }
+
+    fn on_started(&mut self, sound: &Sound, channel: &Channel) {
+        self.last_started.insert(ffi::FFI::unwrap(sound) as usize, Instant::now());
+        self.policy.on_started(sound, channel);
+    }
+}
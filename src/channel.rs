@@ -27,18 +27,25 @@ use libc::{c_int, c_void};
 use ffi;
 use dsp::Dsp;
 use dsp_connection::DspConnection;
+use return_bus::ReturnBus;
 use channel_group::ChannelGroup;
 use fmod_sys;
 use fmod_sys::{MemoryUsageDetails, Sys};
 use vector;
 use sound::Sound;
+use speaker_matrix::SpeakerMatrix;
 use std::mem::transmute;
 use std::default::Default;
 
+/// Size of the buffer used to read channel group names in
+/// [`Channel::snapshot`](struct.Channel.html#method.snapshot).
+pub const CHANNEL_GROUP_NAME_LEN: usize = 256;
+
 /// Structure which contains data for
 /// [`Channel::set_speaker_mix`](struct.Channel.html#method.set_speaker_mix) and
 /// [`Channel::get_speaker_mix`](struct.Channel.html#method.get_speaker_mix)
 #[derive(Debug, PartialEq, PartialOrd, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct SpeakerMixOptions {
     pub front_left : f32,
     pub front_right: f32,
@@ -65,6 +72,34 @@ impl Default for SpeakerMixOptions {
     }
 }
 
+/// Playback state of a channel, returned by
+/// [`Channel::snapshot`](struct.Channel.html#method.snapshot) and used by
+/// [`Sys::restore_channel`](struct.Sys.html#method.restore_channel) to resume a sound after a
+/// load.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ChannelState {
+    /// Playback position in PCM samples.
+    pub position     : u32,
+    pub volume       : f32,
+    pub pan          : f32,
+    pub frequency    : f32,
+    pub mute         : bool,
+    pub paused       : bool,
+    pub priority     : i32,
+    /// Raw [`Mode`](types/struct.Mode.html) flags of the channel.
+    pub mode         : u32,
+    pub loop_count   : i32,
+    /// Loop start and end in PCM samples.
+    pub loop_points  : (u32, u32),
+    /// Position and velocity. `None` for 2D channels.
+    pub attributes_3d: Option<(vector::Vector, vector::Vector)>,
+    /// `None` if no speaker mix has been set, in which case the pan is used.
+    pub speaker_mix  : Option<SpeakerMixOptions>,
+    /// Name of the channel group the channel belongs to.
+    pub channel_group: Option<String>,
+}
+
 /// Structure defining the properties for a reverb source, related to a FMOD channel.
 pub struct ReverbChannelProperties {
     /// [r/w] MIN: -10000 MAX: 1000 DEFAULT: 0
@@ -341,6 +376,55 @@ impl Channel {
         }
     }
 
+    /// Sets the levels of every speaker at once. The matrix must match the channel count of the
+    /// current sound and the speaker mode of the system, and is checked before being sent to
+    /// FMOD.
+    pub fn set_speaker_matrix(&self, matrix: &SpeakerMatrix) -> ::Status {
+        let (speaker_mode, input_channels) = match self.get_matrix_layout() {
+            Ok(l) => l,
+            Err(e) => return e
+        };
+
+        match matrix.validate(speaker_mode, input_channels) {
+            ::Status::Ok => {}
+            e => return e
+        }
+        for speaker in matrix.get_speakers().iter() {
+            let mut levels = matrix.get_levels(*speaker).unwrap_or(&[]).to_vec();
+
+            match self.set_speaker_level(*speaker, &mut levels) {
+                ::Status::Ok => {}
+                e => return e
+            }
+        }
+        ::Status::Ok
+    }
+
+    /// Reads the levels of every speaker for every input channel of the current sound.
+    pub fn get_speaker_matrix(&self) -> Result<SpeakerMatrix, ::Status> {
+        let (speaker_mode, input_channels) = self.get_matrix_layout()?;
+        let mut matrix = SpeakerMatrix::new(speaker_mode, input_channels)?;
+
+        for speaker in matrix.get_speakers().iter() {
+            let levels = self.get_speaker_level(*speaker, input_channels)?;
+
+            matrix.set_levels(*speaker, &levels);
+        }
+        Ok(matrix)
+    }
+
+    fn get_matrix_layout(&self) -> Result<(::SpeakerMode, usize), ::Status> {
+        let speaker_mode = self.get_system_object()?.get_speaker_mode()?;
+
+        match self.get_current_sound() {
+            Ok(sound) => match sound.get_format() {
+                Ok((_, _, channels, _)) => Ok((speaker_mode, channels as usize)),
+                Err(e) => Err(e)
+            },
+            Err(e) => Err(e)
+        }
+    }
+
     pub fn set_input_channel_mix(&self, levels: &mut Vec<f32>) -> ::Status {
         unsafe { ffi::FMOD_Channel_SetInputChannelMix(self.channel, levels.as_mut_ptr(),
                                                       levels.len() as i32) }
@@ -621,6 +705,27 @@ impl Channel {
         }
     }
 
+    /// Sends the output of the channel to a [`ReturnBus`](struct.ReturnBus.html) at the given
+    /// level. As with [`ChannelGroup::create_send`](struct.ChannelGroup.html#method.create_send),
+    /// a level of 0 or less removes the send.
+    pub fn set_send_level(&self, bus: &ReturnBus, level: f32) -> ::Status {
+        match self.get_DSP_head() {
+            Ok(head) => match bus.set_send_level(&head, level) {
+                Ok(_) => ::Status::Ok,
+                Err(e) => e
+            },
+            Err(e) => e
+        }
+    }
+
+    /// Returns the level at which the channel sends to a [`ReturnBus`](struct.ReturnBus.html).
+    pub fn get_send_level(&self, bus: &ReturnBus) -> Result<f32, ::Status> {
+        match self.get_DSP_head() {
+            Ok(head) => bus.get_send_level(&head),
+            Err(e) => Err(e)
+        }
+    }
+
     pub fn add_DSP(&self, dsp: &Dsp) -> Result<DspConnection, ::Status> {
         let mut connection = ::std::ptr::null_mut();
 
@@ -695,6 +800,81 @@ impl Channel {
         }
     }
 
+    /// Reads everything needed to restore the channel later with
+    /// [`Sys::restore_channel`](struct.Sys.html#method.restore_channel).
+    pub fn snapshot(&self) -> Result<ChannelState, ::Status> {
+        let speaker_mix = match self.get_speaker_mix() {
+            Ok(smo) => if smo == Default::default() {
+                None
+            } else {
+                Some(smo)
+            },
+            Err(e) => return Err(e)
+        };
+        let Mode(mode) = self.get_mode()?;
+        let attributes_3d = if mode & ::_3D != 0 {
+            Some(self.get_3D_attributes()?)
+        } else {
+            None
+        };
+        let channel_group = match self.get_channel_group() {
+            Ok(g) => match g.get_name(CHANNEL_GROUP_NAME_LEN) {
+                Ok(n) => Some(n.trim_end_matches('\0').to_owned()),
+                Err(_) => None
+            },
+            Err(_) => None
+        };
+
+        Ok(ChannelState {
+            position: self.get_position(::TIMEUNIT_PCM)? as u32,
+            volume: self.get_volume()?,
+            pan: self.get_pan()?,
+            frequency: self.get_frequency()?,
+            mute: self.get_mute()?,
+            paused: self.get_paused()?,
+            priority: self.get_priority()?,
+            mode: mode,
+            loop_count: self.get_loop_count()?,
+            loop_points: self.get_loop_points(::TIMEUNIT_PCM, ::TIMEUNIT_PCM)?,
+            attributes_3d: attributes_3d,
+            speaker_mix: speaker_mix,
+            channel_group: channel_group,
+        })
+    }
+
+    /// Applies a state read with [`Channel::snapshot`](struct.Channel.html#method.snapshot),
+    /// except for the channel group.
+    pub fn apply_state(&self, state: &ChannelState) -> ::Status {
+        let statuses = [
+            self.set_mode(Mode(state.mode)),
+            self.set_loop_count(state.loop_count),
+            self.set_loop_points(state.loop_points.0, ::TIMEUNIT_PCM, state.loop_points.1,
+                                 ::TIMEUNIT_PCM),
+            self.set_position(state.position as usize, ::TIMEUNIT_PCM),
+            self.set_frequency(state.frequency),
+            self.set_volume(state.volume),
+            self.set_pan(state.pan),
+            match state.speaker_mix {
+                Some(ref smo) => self.set_speaker_mix(smo),
+                None => ::Status::Ok
+            },
+            match state.attributes_3d {
+                Some((ref position, ref velocity)) => self.set_3D_attributes(position, velocity),
+                None => ::Status::Ok
+            },
+            self.set_priority(state.priority),
+            self.set_mute(state.mute),
+            self.set_paused(state.paused),
+        ];
+
+        for status in statuses.iter() {
+            if *status != ::Status::Ok {
+                return *status;
+            }
+        }
+        ::Status::Ok
+    }
+
     pub fn get_memory_info(&self, MemoryBits(memory_bits): MemoryBits,
                            EventMemoryBits(event_memory_bits): EventMemoryBits)
                            -> Result<(u32, MemoryUsageDetails), ::Status> {
@@ -27,6 +27,7 @@ use ffi;
 use channel;
 use dsp;
 use dsp_connection;
+use return_bus;
 use libc::{c_int, c_void};
 use vector;
 use fmod_sys;
@@ -36,8 +37,18 @@ use libc::{c_char};
 use std::default::Default;
 
 /// ChannelGroup object
+///
+/// Only the group returned by
+/// [`Sys::create_channel_group`](struct.Sys.html#method.create_channel_group) owns the FMOD
+/// group: it is released when dropped. Every other `ChannelGroup`, like the ones returned by
+/// [`get_group`](#method.get_group), [`get_parent_group`](#method.get_parent_group),
+/// [`Channel::get_channel_group`](struct.Channel.html#method.get_channel_group) or
+/// [`Sys::get_master_channel_group`](struct.Sys.html#method.get_master_channel_group), is a
+/// handle on a group owned by someone else: dropping it or calling [`release`](#method.release)
+/// on it does nothing.
 pub struct ChannelGroup {
     channel_group: *mut ffi::FMOD_CHANNELGROUP,
+    can_be_deleted: bool,
 }
 
 impl Drop for ChannelGroup {
@@ -48,7 +59,7 @@ impl Drop for ChannelGroup {
 
 impl ffi::FFI<ffi::FMOD_CHANNELGROUP> for ChannelGroup {
     fn wrap(channel_group: *mut ffi::FMOD_CHANNELGROUP) -> ChannelGroup {
-        ChannelGroup {channel_group: channel_group}
+        ChannelGroup {channel_group: channel_group, can_be_deleted: false}
     }
 
     fn unwrap(c: &ChannelGroup) -> *mut ffi::FMOD_CHANNELGROUP {
@@ -56,9 +67,92 @@ impl ffi::FFI<ffi::FMOD_CHANNELGROUP> for ChannelGroup {
     }
 }
 
+pub fn from_ptr_first(channel_group: *mut ffi::FMOD_CHANNELGROUP) -> ChannelGroup {
+    ChannelGroup {channel_group: channel_group, can_be_deleted: true}
+}
+
+/// Iterator over the child groups of a [`ChannelGroup`](struct.ChannelGroup.html), returned by
+/// [`ChannelGroup::groups`](struct.ChannelGroup.html#method.groups).
+pub struct ChannelGroups {
+    group: *mut ffi::FMOD_CHANNELGROUP,
+    index: i32,
+    count: i32,
+}
+
+impl Iterator for ChannelGroups {
+    type Item = ChannelGroup;
+
+    fn next(&mut self) -> Option<ChannelGroup> {
+        while self.index < self.count {
+            let mut group = ::std::ptr::null_mut();
+            let index = self.index;
+
+            self.index += 1;
+            match unsafe { ffi::FMOD_ChannelGroup_GetGroup(self.group, index, &mut group) } {
+                ::Status::Ok if !group.is_null() => return Some(ffi::FFI::wrap(group)),
+                _ => {}
+            }
+        }
+        None
+    }
+}
+
+/// Iterator over the channels playing directly in a [`ChannelGroup`](struct.ChannelGroup.html),
+/// returned by [`ChannelGroup::channels`](struct.ChannelGroup.html#method.channels).
+pub struct Channels {
+    group: *mut ffi::FMOD_CHANNELGROUP,
+    index: i32,
+    count: i32,
+}
+
+impl Iterator for Channels {
+    type Item = channel::Channel;
+
+    fn next(&mut self) -> Option<channel::Channel> {
+        while self.index < self.count {
+            let mut channel = ::std::ptr::null_mut();
+            let index = self.index;
+
+            self.index += 1;
+            match unsafe { ffi::FMOD_ChannelGroup_GetChannel(self.group, index, &mut channel) } {
+                ::Status::Ok if !channel.is_null() => return Some(ffi::FFI::wrap(channel)),
+                _ => {}
+            }
+        }
+        None
+    }
+}
+
+/// Depth-first iterator over a [`ChannelGroup`](struct.ChannelGroup.html) and all its
+/// descendants, returned by [`ChannelGroup::walk`](struct.ChannelGroup.html#method.walk).
+///
+/// Each group comes with its depth, the starting group being at depth 0.
+pub struct Walk {
+    stack: Vec<(usize, ChannelGroup)>,
+}
+
+impl Iterator for Walk {
+    type Item = (usize, ChannelGroup);
+
+    fn next(&mut self) -> Option<(usize, ChannelGroup)> {
+        match self.stack.pop() {
+            Some((depth, group)) => {
+                let children : Vec<ChannelGroup> = group.groups().collect();
+
+                for child in children.into_iter().rev() {
+                    self.stack.push((depth + 1, child));
+                }
+                Some((depth, group))
+            }
+            None => None
+        }
+    }
+}
+
 impl ChannelGroup {
+    /// Releases the group if this handle owns it, see [`ChannelGroup`](struct.ChannelGroup.html).
     pub fn release(&mut self) -> ::Status {
-        if !self.channel_group.is_null() {
+        if self.can_be_deleted && !self.channel_group.is_null() {
             match unsafe { ffi::FMOD_ChannelGroup_Release(self.channel_group) } {
                ::Status::Ok => {
                     self.channel_group = ::std::ptr::null_mut();
@@ -217,7 +311,7 @@ impl ChannelGroup {
         let mut group = ::std::ptr::null_mut();
 
         match unsafe { ffi::FMOD_ChannelGroup_GetGroup(self.channel_group, index, &mut group) } {
-            ::Status::Ok => Ok(ChannelGroup{channel_group: group}),
+            ::Status::Ok => Ok(ffi::FFI::wrap(group)),
             e => Err(e)
         }
     }
@@ -227,7 +321,7 @@ impl ChannelGroup {
 
         match unsafe { ffi::FMOD_ChannelGroup_GetParentGroup(self.channel_group,
                                                              &mut parent_group) } {
-            ::Status::Ok => Ok(ChannelGroup{channel_group: parent_group}),
+            ::Status::Ok => Ok(ffi::FFI::wrap(parent_group)),
             e => Err(e)
         }
     }
@@ -241,6 +335,30 @@ impl ChannelGroup {
         }
     }
 
+    /// Sends the output of the group to a [`ReturnBus`](struct.ReturnBus.html) at the given
+    /// level, or changes the level of the existing send. The level can be changed later with the
+    /// returned connection. As with
+    /// [`Channel::set_send_level`](struct.Channel.html#method.set_send_level), a level of 0 or
+    /// less removes the send and returns `None`.
+    pub fn create_send(&self, bus: &return_bus::ReturnBus, level: f32)
+                       -> Result<Option<dsp_connection::DspConnection>, ::Status> {
+        match self.get_DSP_head() {
+            Ok(head) => bus.set_send_level(&head, level),
+            Err(e) => Err(e)
+        }
+    }
+
+    /// Stops sending to a [`ReturnBus`](struct.ReturnBus.html).
+    pub fn remove_send(&self, bus: &return_bus::ReturnBus) -> ::Status {
+        match self.get_DSP_head() {
+            Ok(head) => match bus.set_send_level(&head, 0f32) {
+                Ok(_) => ::Status::Ok,
+                Err(e) => e
+            },
+            Err(e) => e
+        }
+    }
+
     pub fn add_DSP(&self, dsp: &dsp::Dsp) -> Result<dsp_connection::DspConnection, ::Status> {
         let mut dsp_connection = ::std::ptr::null_mut();
 
@@ -266,6 +384,53 @@ impl ChannelGroup {
         }
     }
 
+    /// Returns the name of the group, without having to guess its length. The buffer grows until
+    /// FMOD doesn't fill it, so names longer than `CHANNEL_GROUP_NAME_LEN` aren't cut.
+    pub fn get_full_name(&self) -> Result<String, ::RStatus> {
+        let mut name_len = channel::CHANNEL_GROUP_NAME_LEN;
+
+        loop {
+            let mut c = vec![0u8; name_len + 1];
+
+            match unsafe { ffi::FMOD_ChannelGroup_GetName(self.channel_group,
+                                                          c.as_mut_ptr() as *mut c_char,
+                                                          name_len as i32) } {
+                ::Status::Ok => {}
+                e => return Err(::RStatus::FMOD(e)),
+            }
+            // a name filling the buffer may have been cut
+            if c[name_len - 1] == 0 {
+                return Ok(from_utf8!(c));
+            }
+            name_len *= 2;
+        }
+    }
+
+    /// Iterates over the direct child groups.
+    pub fn groups(&self) -> ChannelGroups {
+        ChannelGroups {
+            group: self.channel_group,
+            index: 0,
+            count: self.get_num_groups().unwrap_or(0),
+        }
+    }
+
+    /// Iterates over the channels playing directly in this group.
+    pub fn channels(&self) -> Channels {
+        Channels {
+            group: self.channel_group,
+            index: 0,
+            count: self.get_num_channels().unwrap_or(0) as i32,
+        }
+    }
+
+    /// Iterates over this group and all its descendants, depth-first.
+    pub fn walk(&self) -> Walk {
+        Walk {
+            stack: vec![(0, ffi::FFI::wrap(self.channel_group))],
+        }
+    }
+
     pub fn get_num_channels(&self) -> Result<u32, ::Status> {
         let mut num_channels = 0i32;
 
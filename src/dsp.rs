@@ -26,6 +26,8 @@ use ffi;
 use types::*;
 use callbacks::*;
 use dsp_connection;
+use dsp_automation;
+use dsp_preset;
 use fmod_sys;
 use fmod_sys::{MemoryUsageDetails, Sys};
 use std::mem::transmute;
@@ -34,6 +36,7 @@ use libc::{c_char, c_void, c_uint, c_int, c_float};
 use std::default::Default;
 use c_vec::CVec;
 use std::ffi::CString;
+use std::sync::Mutex;
 
 extern "C" fn create_callback(dsp_state: *mut ffi::FMOD_DSP_STATE) -> ::Status {
     unsafe {
@@ -65,13 +68,18 @@ extern "C" fn release_callback(dsp_state: *mut ffi::FMOD_DSP_STATE) -> ::Status
 
             ffi::FMOD_DSP_GetUserData((*dsp_state).instance, &mut tmp);
             if !tmp.is_null() {
-                let callbacks : &mut UserData = transmute(tmp);
-                
-                match callbacks.callbacks.release_callback {
-                    Some(p) => p(&from_state_ptr(::std::ptr::read(
-                        dsp_state as *const ffi::FMOD_DSP_STATE))),
-                    None => ::Status::Ok
-                }
+                let ret = {
+                    let callbacks : &mut UserData = transmute(tmp);
+
+                    match callbacks.callbacks.release_callback {
+                        Some(p) => p(&from_state_ptr(::std::ptr::read(
+                            dsp_state as *const ffi::FMOD_DSP_STATE))),
+                        None => ::Status::Ok
+                    }
+                };
+
+                free_user_data(dsp_state);
+                ret
             } else {
               ::Status::Ok
             }
@@ -217,6 +225,121 @@ extern "C" fn get_parameter_callback(dsp_state: *mut ffi::FMOD_DSP_STATE, index:
     }
 }
 
+unsafe fn get_processor_data<'a>(dsp_state: *mut ffi::FMOD_DSP_STATE)
+                                 -> Option<(&'a UserData, &'a ProcessorData)> {
+    if dsp_state.is_null() || (*dsp_state).instance.is_null() {
+        return None;
+    }
+    let mut tmp = ::std::ptr::null_mut();
+
+    ffi::FMOD_DSP_GetUserData((*dsp_state).instance, &mut tmp);
+    if tmp.is_null() {
+        None
+    } else {
+        let user_data = &*(tmp as *const UserData);
+
+        user_data.processor.as_deref().map(|p| (user_data, p))
+    }
+}
+
+/// Frees the user data allocated when the DSP was created.
+unsafe fn free_user_data(dsp_state: *mut ffi::FMOD_DSP_STATE) {
+    if !dsp_state.is_null() && !(*dsp_state).instance.is_null() {
+        let mut tmp = ::std::ptr::null_mut();
+
+        ffi::FMOD_DSP_GetUserData((*dsp_state).instance, &mut tmp);
+        if !tmp.is_null() {
+            ffi::FMOD_DSP_SetUserData((*dsp_state).instance, ::std::ptr::null_mut());
+            drop(Box::from_raw(tmp as *mut UserData));
+        }
+    }
+}
+
+extern "C" fn processor_release_callback(dsp_state: *mut ffi::FMOD_DSP_STATE) -> ::Status {
+    unsafe { free_user_data(dsp_state); }
+    ::Status::Ok
+}
+
+extern "C" fn processor_reset_callback(dsp_state: *mut ffi::FMOD_DSP_STATE) -> ::Status {
+    if let Some((_, data)) = unsafe { get_processor_data(dsp_state) } {
+        if let Ok(mut processor) = data.processor.lock() {
+            processor.reset();
+        }
+    }
+    ::Status::Ok
+}
+
+extern "C" fn processor_read_callback(dsp_state: *mut ffi::FMOD_DSP_STATE, in_buffer: *mut c_float,
+                                      out_buffer: *mut c_float, length: c_uint,
+                                      in_channels: c_int, out_channels: c_int) -> ::Status {
+    if let Some((_, data)) = unsafe { get_processor_data(dsp_state) } {
+        if in_buffer.is_null() || out_buffer.is_null() || in_channels < 0 || out_channels < 0 {
+            return ::Status::InvalidParam;
+        }
+        let frames = length as usize;
+        let (input, output) = unsafe {
+            (::std::slice::from_raw_parts(in_buffer as *const f32, frames * in_channels as usize),
+             ::std::slice::from_raw_parts_mut(out_buffer, frames * out_channels as usize))
+        };
+
+        match data.processor.lock() {
+            Ok(mut processor) => processor.process(input, output, frames, in_channels as usize,
+                                                   out_channels as usize),
+            Err(_) => {
+                for sample in output.iter_mut() {
+                    *sample = 0f32;
+                }
+            }
+        }
+    }
+    ::Status::Ok
+}
+
+extern "C" fn processor_set_parameter_callback(dsp_state: *mut ffi::FMOD_DSP_STATE, index: c_int,
+                                               value: c_float) -> ::Status {
+    match unsafe { get_processor_data(dsp_state) } {
+        Some((_, data)) => match data.processor.lock() {
+            Ok(mut processor) => processor.set_parameter(index, value),
+            Err(_) => ::Status::Internal
+        },
+        None => ::Status::InvalidHandle
+    }
+}
+
+extern "C" fn processor_get_parameter_callback(dsp_state: *mut ffi::FMOD_DSP_STATE, index: c_int,
+                                               value: *mut c_float,
+                                               value_str: *mut c_char) -> ::Status {
+    match unsafe { get_processor_data(dsp_state) } {
+        Some((user_data, data)) => match data.processor.lock() {
+            Ok(processor) => {
+                let v = processor.get_parameter(index);
+
+                unsafe {
+                    if !value.is_null() {
+                        *value = v;
+                    }
+                    if !value_str.is_null() {
+                        // FMOD gives a 16 bytes buffer
+                        let mut text = match user_data.parameters.get(index as usize) {
+                            Some(parameter) => parameter.format_value(v),
+                            None => format!("{:.2}", v)
+                        }.into_bytes();
+
+                        text.truncate(15);
+                        for (pos, c) in text.iter().enumerate() {
+                            *value_str.add(pos) = *c as c_char;
+                        }
+                        *value_str.add(text.len()) = 0;
+                    }
+                }
+                ::Status::Ok
+            }
+            Err(_) => ::Status::Internal
+        },
+        None => ::Status::InvalidHandle
+    }
+}
+
 #[allow(unused_variables)]
 extern "C" fn config_callback(dsp_state: *mut ffi::FMOD_DSP_STATE, hwnd: *mut c_void,
                               show: c_int) -> ::Status {
@@ -226,6 +349,11 @@ extern "C" fn config_callback(dsp_state: *mut ffi::FMOD_DSP_STATE, hwnd: *mut c_
 struct UserData {
     callbacks: DspCallbacks,
     user_data: *mut c_void,
+    processor: Option<Box<ProcessorData>>,
+    parameters: Vec<DspParameterDesc>,
+    // FMOD keeps pointing to the parameter descriptions and to their strings
+    param_desc: Vec<ffi::FMOD_DSP_PARAMETERDESC>,
+    param_strings: Vec<CString>,
 }
 
 impl UserData {
@@ -233,8 +361,66 @@ impl UserData {
         UserData {
             callbacks: DspCallbacks::new(),
             user_data: ::std::ptr::null_mut(),
+            processor: None,
+            parameters: Vec::new(),
+            param_desc: Vec::new(),
+            param_strings: Vec::new(),
         }
     }
+
+    /// Converts and stores the parameters, returns the pointer to give to FMOD.
+    fn set_parameters(&mut self, parameters: Vec<DspParameterDesc>)
+                      -> Result<*mut ffi::FMOD_DSP_PARAMETERDESC, ::RStatus> {
+        let (param_desc, param_strings) = get_parameters_ffi(&parameters)?;
+
+        self.parameters = parameters;
+        self.param_desc = param_desc;
+        self.param_strings = param_strings;
+        if self.param_desc.is_empty() {
+            Ok(::std::ptr::null_mut())
+        } else {
+            Ok(self.param_desc.as_mut_ptr())
+        }
+    }
+}
+
+/// The processor of a DSP created from a [`DspProcessor`](trait.DspProcessor.html).
+struct ProcessorData {
+    processor: Mutex<Box<dyn DspProcessor>>,
+}
+
+/// Custom DSP effect holding its own state, created with
+/// [`Sys::create_dsp_from_processor`](struct.Sys.html#method.create_dsp_from_processor).
+///
+/// The processor is called from the FMOD mixer thread; it is dropped when the DSP is released.
+pub trait DspProcessor: Send {
+    /// Processes `frames` frames. `input` is interleaved with `in_channels` channels and `output`
+    /// with `out_channels` channels.
+    fn process(&mut self, input: &[f32], output: &mut [f32], frames: usize, in_channels: usize,
+               out_channels: usize);
+
+    /// Called by [`Dsp::set_parameter`](struct.Dsp.html#method.set_parameter).
+    fn set_parameter(&mut self, _index: i32, _value: f32) -> ::Status {
+        ::Status::InvalidParam
+    }
+
+    /// Called by [`Dsp::get_parameter`](struct.Dsp.html#method.get_parameter).
+    fn get_parameter(&self, _index: i32) -> f32 {
+        0f32
+    }
+
+    /// Called by [`Dsp::reset`](struct.Dsp.html#method.reset) to clear any history buffer.
+    fn reset(&mut self) {}
+
+    /// Name of the unit, truncated to 31 bytes.
+    fn get_name(&self) -> String {
+        "rust processor".to_owned()
+    }
+
+    /// Parameters the DSP exposes.
+    fn get_parameters(&self) -> Vec<DspParameterDesc> {
+        Vec::new()
+    }
 }
 
 struct DspCallbacks {
@@ -275,6 +461,46 @@ impl Clone for DspCallbacks {
     }
 }
 
+#[derive(Debug, PartialEq, Clone)]
+/// How the value of a [`DspParameterDesc`](struct.DspParameterDesc.html) is interpreted. FMOD
+/// only stores floats; the kind is used to validate and display the value.
+pub enum DspParameterKind {
+    /// Any value between min and max.
+    Float,
+    /// Integer values from min to max, each one having a name (ie "Lowpass", "Highpass").
+    Int(Vec<String>),
+    /// 0 or 1.
+    Bool,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+/// Information about a DSP parameter, returned by
+/// [`Dsp::get_parameter_info`](struct.Dsp.html#method.get_parameter_info).
+pub struct ParameterInfo {
+    /// Name of the parameter (ie "Cutoff frequency").
+    pub name       : String,
+    /// Unit of the value (ie "hz").
+    pub label      : String,
+    /// Help text of the parameter, truncated to 255 bytes.
+    pub description: String,
+    pub min        : f32,
+    pub max        : f32,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+/// Information about a DSP unit, returned by [`Dsp::get_info`](struct.Dsp.html#method.get_info).
+pub struct DspInfo {
+    pub name         : String,
+    /// Version number of the DSP, in the xxxxyyyy format.
+    pub version      : u32,
+    /// Number of channels the unit was initialized with, 0 if it processes whatever it receives.
+    pub channels     : i32,
+    /// Preferred width of the configuration dialog, 0 if there is none.
+    pub config_width : i32,
+    /// Preferred height of the configuration dialog, 0 if there is none.
+    pub config_height: i32,
+}
+
 #[derive(Debug, PartialEq, Clone)]
 /// Structure to define a parameter for a DSP unit.
 pub struct DspParameterDesc {
@@ -284,12 +510,15 @@ pub struct DspParameterDesc {
     pub max         : f32,
     /// [w] Default value of parameter
     pub default_val : f32,
-    /// [w] Name of the parameter to be displayed (ie "Cutoff frequency")
+    /// [w] Name of the parameter to be displayed (ie "Cutoff frequency"), at most 15 bytes.
     pub name        : String,
-    /// [w] Short string to be put next to value to denote the unit type (ie "hz")
+    /// [w] Short string to be put next to value to denote the unit type (ie "hz"), at most 15
+    /// bytes.
     pub label       : String,
     /// [w] Description of the parameter to be displayed as a help item / tooltip for this parameter
     pub description : String,
+    /// How the value is interpreted.
+    pub kind        : DspParameterKind,
 }
 
 impl Default for DspParameterDesc {
@@ -301,6 +530,104 @@ impl Default for DspParameterDesc {
             name: String::new(),
             label: String::new(),
             description: String::new(),
+            kind: DspParameterKind::Float,
+        }
+    }
+}
+
+impl DspParameterDesc {
+    pub fn float(name: &str, min: f32, max: f32, default_val: f32) -> DspParameterDesc {
+        DspParameterDesc {
+            min: min,
+            max: max,
+            default_val: default_val,
+            name: name.to_owned(),
+            .. Default::default()
+        }
+    }
+
+    /// Creates a parameter taking the index of one of `values`.
+    pub fn int_enum(name: &str, values: &[&str], default_val: usize) -> DspParameterDesc {
+        DspParameterDesc {
+            min: 0f32,
+            max: values.len().saturating_sub(1) as f32,
+            default_val: default_val as f32,
+            name: name.to_owned(),
+            kind: DspParameterKind::Int(values.iter().map(|v| (*v).to_owned()).collect()),
+            .. Default::default()
+        }
+    }
+
+    pub fn bool(name: &str, default_val: bool) -> DspParameterDesc {
+        DspParameterDesc {
+            min: 0f32,
+            max: 1f32,
+            default_val: if default_val { 1f32 } else { 0f32 },
+            name: name.to_owned(),
+            kind: DspParameterKind::Bool,
+            .. Default::default()
+        }
+    }
+
+    pub fn with_label(mut self, label: &str) -> DspParameterDesc {
+        self.label = label.to_owned();
+        self
+    }
+
+    pub fn with_description(mut self, description: &str) -> DspParameterDesc {
+        self.description = description.to_owned();
+        self
+    }
+
+    /// Checks that the parameter can be given to FMOD: name and label must fit in 15 bytes, the
+    /// description must not contain a nul byte, min must not be above max and the default value
+    /// must be in range and match the kind.
+    pub fn validate(&self) -> ::Status {
+        if self.name.len() > 15 || self.label.len() > 15 || self.description.contains('\0') {
+            return ::Status::InvalidParam;
+        }
+        if !self.min.is_finite() || !self.max.is_finite() || !self.default_val.is_finite() {
+            return ::Status::InvalidFloat;
+        }
+        if self.min > self.max || self.default_val < self.min || self.default_val > self.max {
+            return ::Status::InvalidParam;
+        }
+        match self.kind {
+            DspParameterKind::Float => ::Status::Ok,
+            DspParameterKind::Int(ref values) => {
+                if self.min.fract() != 0f32 || self.max.fract() != 0f32 ||
+                   self.default_val.fract() != 0f32 ||
+                   (!values.is_empty() && values.len() != (self.max - self.min) as usize + 1) {
+                    ::Status::InvalidParam
+                } else {
+                    ::Status::Ok
+                }
+            }
+            DspParameterKind::Bool => {
+                if self.min == 0f32 && self.max == 1f32 && self.default_val.fract() == 0f32 {
+                    ::Status::Ok
+                } else {
+                    ::Status::InvalidParam
+                }
+            }
+        }
+    }
+
+    /// Returns the value as it should be displayed: the name of the value for `Int` parameters,
+    /// "on"/"off" for `Bool` ones.
+    pub fn format_value(&self, value: f32) -> String {
+        match self.kind {
+            DspParameterKind::Float => format!("{:.2}", value),
+            DspParameterKind::Int(ref values) => {
+                let index = (value - self.min).round();
+
+                if index >= 0f32 && (index as usize) < values.len() {
+                    values[index as usize].clone()
+                } else {
+                    format!("{}", value.round() as i32)
+                }
+            }
+            DspParameterKind::Bool => if value >= 0.5f32 { "on" } else { "off" }.to_owned()
         }
     }
 }
@@ -340,6 +667,7 @@ pub fn from_parameter_ptr(dsp_parameter: *mut ffi::FMOD_DSP_PARAMETERDESC) -> Re
                 name,
                 label,
                 description,
+                kind: DspParameterKind::Float,
             })
         }
     } else {
@@ -412,10 +740,9 @@ pub struct DspDescription {
     /// not process data, or reset a cursor position internally if it is reading data from a certain
     /// source. Can be null.
     pub set_position        : DspSetPositionCallback,
-    /// [w] Number of parameters used in this filter. The user finds this with DSP::getNumParameters
-    pub num_parameters      : i32,
-    /// [w] Variable number of parameter structures.
-    pub param_desc          : DspParameterDesc,
+    /// [w] Parameters of the filter. The user finds their number with
+    /// [`Dsp::get_num_parameters`](struct.Dsp.html#method.get_num_parameters).
+    pub param_desc          : Vec<DspParameterDesc>,
     /// [w] This is called when the user calls DSP::setParameter. Can be null.
     pub set_parameter       : DspSetParamCallback,
     /// [w] This is called when the user calls DSP::getParameter. Can be null.
@@ -427,9 +754,6 @@ pub struct DspDescription {
     pub config_width        : i32,
     /// [w] Height of config dialog graphic if there is one. 0 otherwise.
     pub config_height       : i32,
-    /// [w] Optional. Specify 0 to ignore. This is user data to be attached to the DSP unit during
-    /// creation. Access via DSP::getUserData.
-    user_data               : Box<UserData>,
 }
 
 impl Default for DspDescription {
@@ -443,45 +767,44 @@ impl Default for DspDescription {
             reset: None,
             read: None,
             set_position: None,
-            num_parameters: 0i32,
-            param_desc: Default::default(),
+            param_desc: Vec::new(),
             set_parameter: None,
             get_parameter: None,
             config: None,
             config_width: 0i32,
             config_height: 0i32,
-            user_data: Box::new(UserData::new()),
         }
     }
 }
 
-pub fn get_description_ffi(dsp_description: &mut DspDescription) -> ffi::FMOD_DSP_DESCRIPTION {
-    let mut tmp_s = dsp_description.name.as_bytes().to_vec();
-
-    tmp_s.truncate(32);
-    tmp_s.reserve_exact(32);
-    ffi::FMOD_DSP_DESCRIPTION {
-        name: {
-            let mut slice : [i8; 32] = [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-                                        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
-            let mut it = 0;
-
-            for tmp in tmp_s.iter() {
-                slice[it] = *tmp as i8;
-                it += 1;
-            }
-            slice
-        },
+/// Returns the description to give to FMOD and its user data. The user data belongs to the DSP
+/// once created and is freed when it is released; it has to be given to
+/// [`free_user_data_ptr`](fn.free_user_data_ptr.html) if the creation failed.
+pub fn get_description_ffi(dsp_description: &DspDescription)
+                           -> Result<(ffi::FMOD_DSP_DESCRIPTION, *mut c_void), ::RStatus> {
+    let mut user_data = Box::new(UserData::new());
+    let param_ptr = user_data.set_parameters(dsp_description.param_desc.clone())?;
+
+    user_data.callbacks.create_callback = dsp_description.create;
+    user_data.callbacks.release_callback = dsp_description.release;
+    user_data.callbacks.reset_callback = dsp_description.reset;
+    user_data.callbacks.read_callback = dsp_description.read;
+    user_data.callbacks.set_pos_callback = dsp_description.set_position;
+    user_data.callbacks.set_param_callback = dsp_description.set_parameter;
+    user_data.callbacks.get_param_callback = dsp_description.get_parameter;
+
+    let user_data = Box::into_raw(user_data) as *mut c_void;
+
+    Ok((ffi::FMOD_DSP_DESCRIPTION {
+        name: get_name_ffi(&dsp_description.name),
         version: dsp_description.version,
         channels: dsp_description.channels,
         create: match dsp_description.create {
             Some(_) => Some(create_callback as extern "C" fn(*mut _) -> _),
             None => None
         },
-        release: match dsp_description.release {
-            Some(_) => Some(release_callback as extern "C" fn(*mut _) -> _),
-            None => None
-        },
+        // always set to free the user data
+        release: Some(release_callback as extern "C" fn(*mut _) -> _),
         reset: match dsp_description.reset {
             Some(_) => Some(reset_callback as extern "C" fn(*mut _) -> _),
             None => None
@@ -494,9 +817,8 @@ pub fn get_description_ffi(dsp_description: &mut DspDescription) -> ffi::FMOD_DS
             Some(_) => Some(set_position_callback as extern "C" fn(*mut _, _) -> _),
             None => None
         },
-        num_parameters: dsp_description.num_parameters,
-        param_desc: &mut get_parameter_ffi(&dsp_description.param_desc).expect("get_parameter_ffi failed")
-                    as *mut ffi::FMOD_DSP_PARAMETERDESC,
+        num_parameters: dsp_description.param_desc.len() as c_int,
+        param_desc: param_ptr,
         set_parameter: match dsp_description.set_parameter {
             Some(_) => Some(set_parameter_callback as extern "C" fn(*mut _, _, _) -> _),
             None => None
@@ -512,16 +834,84 @@ pub fn get_description_ffi(dsp_description: &mut DspDescription) -> ffi::FMOD_DS
         },
         config_height: dsp_description.config_height,
         config_width: dsp_description.config_width,
-        user_data: {
-            dsp_description.user_data.callbacks.create_callback = dsp_description.create;
-            dsp_description.user_data.callbacks.release_callback = dsp_description.release;
-            dsp_description.user_data.callbacks.reset_callback = dsp_description.reset;
-            dsp_description.user_data.callbacks.read_callback = dsp_description.read;
-            dsp_description.user_data.callbacks.set_pos_callback = dsp_description.set_position;
-            dsp_description.user_data.callbacks.set_param_callback = dsp_description.set_parameter;
-            dsp_description.user_data.callbacks.get_param_callback = dsp_description.get_parameter;
-            unsafe { transmute::<&mut UserData, *mut c_void>(&mut *dsp_description.user_data) }
-        },
+        user_data: user_data,
+    }, user_data))
+}
+
+/// Converts the parameters, the returned strings have to be kept alive as long as the
+/// descriptions.
+fn get_parameters_ffi(parameters: &[DspParameterDesc])
+                      -> Result<(Vec<ffi::FMOD_DSP_PARAMETERDESC>, Vec<CString>), ::RStatus> {
+    let mut param_desc = Vec::with_capacity(parameters.len());
+    let mut param_strings = Vec::with_capacity(parameters.len());
+
+    for parameter in parameters.iter() {
+        match parameter.validate() {
+            ::Status::Ok => {}
+            e => return Err(::RStatus::FMOD(e))
+        }
+        let mut desc = get_parameter_ffi(parameter)?;
+        let description = match CString::new(parameter.description.clone()) {
+            Ok(s) => s,
+            Err(e) => return Err(::RStatus::Other(format!("Issue with dsp_parameter: {}", e))),
+        };
+
+        desc.description = description.as_ptr();
+        param_desc.push(desc);
+        param_strings.push(description);
+    }
+    Ok((param_desc, param_strings))
+}
+
+fn get_name_ffi(name: &str) -> [c_char; 32] {
+    let mut slice = [0 as c_char; 32];
+
+    for (it, c) in name.as_bytes().iter().take(31).enumerate() {
+        slice[it] = *c as c_char;
+    }
+    slice
+}
+
+/// Returns the description of a DSP calling the processor and the pointer to give to
+/// [`free_user_data_ptr`](fn.free_user_data_ptr.html) if the DSP couldn't be created.
+pub fn get_processor_description_ffi(processor: Box<dyn DspProcessor>)
+                                     -> Result<(ffi::FMOD_DSP_DESCRIPTION, *mut c_void), ::RStatus> {
+    let name = processor.get_name();
+    let mut user_data = Box::new(UserData::new());
+    let param_ptr = user_data.set_parameters(processor.get_parameters())?;
+    let num_parameters = user_data.param_desc.len() as c_int;
+
+    user_data.processor = Some(Box::new(ProcessorData {
+        processor: Mutex::new(processor),
+    }));
+
+    let user_data = Box::into_raw(user_data) as *mut c_void;
+
+    Ok((ffi::FMOD_DSP_DESCRIPTION {
+        name: get_name_ffi(&name),
+        version: 0,
+        channels: 0,
+        create: None,
+        release: Some(processor_release_callback as extern "C" fn(*mut _) -> _),
+        reset: Some(processor_reset_callback as extern "C" fn(*mut _) -> _),
+        read: Some(processor_read_callback as extern "C" fn(*mut _, *mut _, *mut _, _, _, _) -> _),
+        set_position: None,
+        num_parameters: num_parameters,
+        param_desc: param_ptr,
+        set_parameter: Some(processor_set_parameter_callback as extern "C" fn(*mut _, _, _) -> _),
+        get_parameter: Some(processor_get_parameter_callback
+                            as extern "C" fn(*mut _, _, *mut _, *mut _) -> _),
+        config: None,
+        config_width: 0,
+        config_height: 0,
+        user_data: user_data,
+    }, user_data))
+}
+
+/// Frees the user data created with a description when the DSP couldn't be created.
+pub fn free_user_data_ptr(user_data: *mut c_void) {
+    if !user_data.is_null() {
+        unsafe { drop(Box::from_raw(user_data as *mut UserData)); }
     }
 }
 
@@ -552,14 +942,17 @@ pub struct DspState {
     pub speaker_mask: u16,
 }
 
+fn c_chars_to_string(chars: &[c_char]) -> String {
+    let bytes : Vec<u8> = chars.iter().take_while(|c| **c != 0).map(|c| *c as u8).collect();
+
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
 pub fn from_ptr_first(dsp: *mut ffi::FMOD_DSP) -> Dsp {
     Dsp {
         dsp: dsp,
         can_be_deleted: true,
-        user_data: UserData {
-            callbacks: DspCallbacks::new(),
-            user_data: ::std::ptr::null_mut()
-        }
+        user_data: UserData::new()
     }
 }
 
@@ -575,10 +968,7 @@ impl ffi::FFI<ffi::FMOD_DSP> for Dsp {
         Dsp {
             dsp: dsp,
             can_be_deleted: false,
-            user_data: UserData {
-                callbacks: DspCallbacks::new(),
-                user_data: ::std::ptr::null_mut()
-            }
+            user_data: UserData::new()
         }
     }
 
@@ -807,6 +1197,33 @@ impl Dsp {
         unsafe { ffi::FMOD_DSP_SetParameter(self.dsp, index, value) }
     }
 
+    /// Saves the value of every parameter. See [`DspPreset`](struct.DspPreset.html).
+    pub fn to_preset(&self) -> Result<dsp_preset::DspPreset, ::Status> {
+        dsp_preset::to_preset(self)
+    }
+
+    /// Applies a preset saved from a DSP of the same type. Returns `Status::InvalidParam` if the
+    /// type differs or if the preset contains an unknown parameter, in which case nothing is
+    /// changed.
+    pub fn apply_preset(&self, preset: &dsp_preset::DspPreset) -> ::Status {
+        dsp_preset::apply_preset(self, preset)
+    }
+
+    /// Returns a lane changing the parameter over time with
+    /// [`set_parameter`](#method.set_parameter). Nothing happens until the lane is updated, see
+    /// [`AutomationLane`](struct.AutomationLane.html).
+    ///
+    /// The automation is checked with
+    /// [`Automation::validate`](enum.Automation.html#method.validate) first, so a NaN value or
+    /// rate never reaches the DSP.
+    pub fn automate(&self, index: i32, automation: dsp_automation::Automation)
+                    -> Result<dsp_automation::AutomationLane, ::Status> {
+        match automation.validate() {
+            ::Status::Ok => Ok(dsp_automation::new_lane(self, index, automation)),
+            e => Err(e)
+        }
+    }
+
     /// value result depends directly on the index argument,
     /// index argument depends on your DSP type, it is a value from one of the following enums:
     /// 
@@ -858,40 +1275,46 @@ impl Dsp {
         }
     }
 
-    pub fn get_parameter_info(&self, index: i32, name: &str, label: &str,
-                              description_len: usize) -> Result<(String, f32, f32), ::RStatus> {
+    /// Returns the name, label, description and range of a parameter.
+    pub fn get_parameter_info(&self, index: i32) -> Result<ParameterInfo, ::Status> {
+        let mut name = [0 as c_char; 16];
+        let mut label = [0 as c_char; 16];
+        let mut description = [0 as c_char; 256];
         let mut min = 0f32;
         let mut max = 0f32;
-        let t_name = name.clone();
-        let t_label = label.clone();
-        let mut description = Vec::with_capacity(description_len + 1);
-
-        for _ in 0..(description_len + 1) {
-            description.push(0);
-        }
 
-        match unsafe { ffi::FMOD_DSP_GetParameterInfo(self.dsp, index,
-                                                      t_name.as_ptr() as *mut c_char,
-                                                      t_label.as_ptr() as *mut c_char,
-                                                      description.as_mut_ptr() as *mut c_char,
-                                                      description_len as i32, &mut min,
+        match unsafe { ffi::FMOD_DSP_GetParameterInfo(self.dsp, index, name.as_mut_ptr(),
+                                                      label.as_mut_ptr(), description.as_mut_ptr(),
+                                                      description.len() as c_int - 1, &mut min,
                                                       &mut max) } {
-            ::Status::Ok => Ok((from_utf8!(description), min, max)),
-            e => Err(::RStatus::FMOD(e)),
+            ::Status::Ok => Ok(ParameterInfo {
+                name: c_chars_to_string(&name),
+                label: c_chars_to_string(&label),
+                description: c_chars_to_string(&description),
+                min: min,
+                max: max,
+            }),
+            e => Err(e),
         }
     }
 
-    pub fn get_info(&self, name: &str) -> Result<(u32, i32, i32, i32), ::Status> {
+    pub fn get_info(&self) -> Result<DspInfo, ::Status> {
+        let mut name = [0 as c_char; 32];
         let mut version = 0u32;
         let mut channels = 0i32;
         let mut config_width = 0i32;
         let mut config_height = 0i32;
-        let tmp_n = name.clone();
 
-        match unsafe { ffi::FMOD_DSP_GetInfo(self.dsp, tmp_n.as_ptr() as *mut c_char, &mut version,
+        match unsafe { ffi::FMOD_DSP_GetInfo(self.dsp, name.as_mut_ptr(), &mut version,
                                              &mut channels, &mut config_width,
-            &mut config_height) } {
-            ::Status::Ok => Ok((version, channels, config_width, config_height)),
+                                             &mut config_height) } {
+            ::Status::Ok => Ok(DspInfo {
+                name: c_chars_to_string(&name),
+                version: version,
+                channels: channels,
+                config_width: config_width,
+                config_height: config_height,
+            }),
             e => Err(e)
         }
     }
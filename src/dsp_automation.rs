@@ -0,0 +1,399 @@
+/*
+* Rust-FMOD - Copyright (c) 2018 Gomez Guillaume.
+*
+* The Original software, FmodEx library, is provided by FIRELIGHT TECHNOLOGIES.
+*
+* This software is provided 'as-is', without any express or implied warranty.
+* In no event will the authors be held liable for any damages arising from
+* the use of this software.
+*
+* Permission is granted to anyone to use this software for any purpose,
+* including commercial applications, and to alter it and redistribute it
+* freely, subject to the following restrictions:
+*
+* 1. The origin of this software must not be misrepresented; you must not claim
+*    that you wrote the original software. If you use this software in a product,
+*    an acknowledgment in the product documentation would be appreciated but is
+*    not required.
+*
+* 2. Altered source versions must be plainly marked as such, and must not be
+*    misrepresented as being the original software.
+*
+* 3. This notice may not be removed or altered from any source distribution.
+*/
+
+use ffi;
+use dsp::Dsp;
+use fmod_sys::Sys;
+use std::f32::consts::PI;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+/// Waveform of an [`Automation::Lfo`](enum.Automation.html).
+pub enum LfoShape {
+    Sine,
+    Triangle,
+    Square,
+    /// A new random value is picked at every period and held until the next one.
+    Random,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+/// How a DSP parameter changes over time. Used with
+/// [`Dsp::automate`](struct.Dsp.html#method.automate).
+pub enum Automation {
+    /// Goes linearly from `from` to `to`, then stays at `to`.
+    Ramp {
+        from: f32,
+        to: f32,
+        duration: Duration,
+    },
+    /// Linear interpolation between (time, value) keyframes, sorted by time. The value of the
+    /// first keyframe is used before it and the value of the last one after it.
+    Envelope(Vec<(Duration, f32)>),
+    /// Oscillates between `center - depth` and `center + depth`, `rate` times per second.
+    Lfo {
+        shape: LfoShape,
+        rate: f32,
+        depth: f32,
+        center: f32,
+    },
+}
+
+fn to_secs(duration: Duration) -> f32 {
+    duration.as_secs() as f32 + duration.subsec_nanos() as f32 / 1_000_000_000f32
+}
+
+/// Returns a pseudo-random value in [-1, 1] for the given period.
+fn random_value(period: u64) -> f32 {
+    let mut x = period.wrapping_add(0x9e37_79b9_7f4a_7c15);
+
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^= x >> 31;
+    (x >> 40) as f32 / (1u64 << 23) as f32 - 1f32
+}
+
+impl Automation {
+    /// Returns the value of the parameter `elapsed` after the start of the automation.
+    pub fn get_value(&self, elapsed: Duration) -> f32 {
+        match *self {
+            Automation::Ramp { from, to, duration } => {
+                if elapsed >= duration {
+                    to
+                } else {
+                    from + (to - from) * to_secs(elapsed) / to_secs(duration)
+                }
+            }
+            Automation::Envelope(ref keyframes) => {
+                let next = match keyframes.iter().position(|k| k.0 > elapsed) {
+                    Some(next) => next,
+                    None => return keyframes.last().map(|k| k.1).unwrap_or(0f32)
+                };
+
+                if next == 0 {
+                    return keyframes[0].1;
+                }
+                let (start, from) = keyframes[next - 1];
+                let (end, to) = keyframes[next];
+
+                from + (to - from) * to_secs(elapsed - start) / to_secs(end - start)
+            }
+            Automation::Lfo { shape, rate, depth, center } => {
+                let position = to_secs(elapsed) * rate;
+                let phase = position.fract();
+                let wave = match shape {
+                    LfoShape::Sine => (2f32 * PI * phase).sin(),
+                    LfoShape::Triangle => if phase < 0.5f32 {
+                        4f32 * phase - 1f32
+                    } else {
+                        3f32 - 4f32 * phase
+                    },
+                    LfoShape::Square => if phase < 0.5f32 { 1f32 } else { -1f32 },
+                    LfoShape::Random => random_value(position as u64)
+                };
+
+                center + depth * wave
+            }
+        }
+    }
+
+    /// Returns how long the automation lasts, `None` for an LFO.
+    pub fn get_duration(&self) -> Option<Duration> {
+        match *self {
+            Automation::Ramp { duration, .. } => Some(duration),
+            Automation::Envelope(ref keyframes) => {
+                Some(keyframes.last().map(|k| k.0).unwrap_or_default())
+            }
+            Automation::Lfo { .. } => None
+        }
+    }
+
+    /// Checks that the values are finite and that envelope keyframes are sorted.
+    pub fn validate(&self) -> ::Status {
+        let finite = match *self {
+            Automation::Ramp { from, to, .. } => from.is_finite() && to.is_finite(),
+            Automation::Envelope(ref keyframes) => {
+                if keyframes.windows(2).any(|w| w[0].0 > w[1].0) {
+                    return ::Status::InvalidParam;
+                }
+                keyframes.iter().all(|k| k.1.is_finite())
+            }
+            Automation::Lfo { rate, depth, center, .. } => {
+                rate.is_finite() && rate >= 0f32 && depth.is_finite() && center.is_finite()
+            }
+        };
+
+        if finite {
+            ::Status::Ok
+        } else {
+            ::Status::InvalidFloat
+        }
+    }
+}
+
+/// An [`Automation`](enum.Automation.html) applied to one parameter of a DSP, created with
+/// [`Dsp::automate`](struct.Dsp.html#method.automate) once the automation passed
+/// [`Automation::validate`](enum.Automation.html#method.validate).
+///
+/// The lane doesn't keep the DSP alive. It has to be advanced with
+/// [`update`](#method.update), [`update_by`](#method.update_by) or
+/// [`update_clock`](#method.update_clock), typically alongside
+/// [`Sys::update`](struct.Sys.html#method.update), or through an
+/// [`Automator`](struct.Automator.html).
+///
+/// ```ignore
+/// let sweep = lowpass.automate(rfmod::DspLowPass::Cutoff as i32, rfmod::Automation::Ramp {
+///     from: 5000f32,
+///     to: 200f32,
+///     duration: Duration::from_secs(2),
+/// }).unwrap();
+/// ```
+pub struct AutomationLane {
+    dsp: Dsp,
+    index: i32,
+    automation: Automation,
+    elapsed: Duration,
+    last_update: Option<Instant>,
+    last_clock: Option<u64>,
+}
+
+pub fn new_lane(dsp: &Dsp, index: i32, automation: Automation) -> AutomationLane {
+    AutomationLane {
+        dsp: ffi::FFI::wrap(ffi::FFI::unwrap(dsp)),
+        index: index,
+        automation: automation,
+        elapsed: Duration::from_millis(0),
+        last_update: None,
+        last_clock: None,
+    }
+}
+
+impl AutomationLane {
+    /// Applies the value at the current position.
+    pub fn apply(&self) -> ::Status {
+        self.dsp.set_parameter(self.index, self.automation.get_value(self.elapsed))
+    }
+
+    /// Advances the lane with the time elapsed since the previous call.
+    pub fn update(&mut self) -> ::Status {
+        let now = Instant::now();
+        let elapsed = match self.last_update {
+            Some(last) => now.duration_since(last),
+            None => Duration::from_millis(0),
+        };
+
+        self.last_update = Some(now);
+        self.update_by(elapsed)
+    }
+
+    /// Advances the lane as if `elapsed` had passed since the previous update.
+    pub fn update_by(&mut self, elapsed: Duration) -> ::Status {
+        self.elapsed += elapsed;
+        self.apply()
+    }
+
+    /// Advances the lane with the number of samples mixed since the previous call, read from
+    /// [`Sys::get_DSP_clock`](struct.Sys.html#method.get_DSP_clock). Unlike
+    /// [`update`](#method.update), it follows the mixer and stops when the output is paused.
+    pub fn update_clock(&mut self, sys: &Sys) -> ::Status {
+        let (clock, sample_rate) = match get_clock(sys) {
+            Ok(c) => c,
+            Err(e) => return e
+        };
+        let elapsed = match self.last_clock {
+            Some(last) => samples_to_duration(clock.saturating_sub(last), sample_rate),
+            None => Duration::from_millis(0)
+        };
+
+        self.last_clock = Some(clock);
+        self.update_by(elapsed)
+    }
+
+    /// Goes back to the start of the automation.
+    pub fn restart(&mut self) {
+        self.elapsed = Duration::from_millis(0);
+        self.last_update = None;
+        self.last_clock = None;
+    }
+
+    pub fn get_elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Returns true once a ramp or envelope reached its end. LFOs never finish.
+    pub fn is_finished(&self) -> bool {
+        match self.automation.get_duration() {
+            Some(duration) => self.elapsed >= duration,
+            None => false
+        }
+    }
+
+    pub fn get_index(&self) -> i32 {
+        self.index
+    }
+
+    pub fn get_automation(&self) -> &Automation {
+        &self.automation
+    }
+}
+
+fn get_clock(sys: &Sys) -> Result<(u64, u32), ::Status> {
+    let clock = match sys.get_DSP_clock() {
+        Ok((hi, lo)) => (hi as u64) << 32 | lo as u64,
+        Err(e) => return Err(e)
+    };
+
+    match sys.get_software_format() {
+        Ok(format) if format.sample_rate > 0 => Ok((clock, format.sample_rate as u32)),
+        Ok(_) => Err(::Status::Format),
+        Err(e) => Err(e)
+    }
+}
+
+fn samples_to_duration(samples: u64, sample_rate: u32) -> Duration {
+    Duration::new(samples / sample_rate as u64,
+                  ((samples % sample_rate as u64) * 1_000_000_000 / sample_rate as u64) as u32)
+}
+
+/// Runs several [`AutomationLane`](struct.AutomationLane.html)s together. Finished lanes are
+/// dropped after their last value is applied.
+pub struct Automator {
+    lanes: Vec<Option<AutomationLane>>,
+    last_update: Option<Instant>,
+    last_clock: Option<u64>,
+}
+
+impl Default for Automator {
+    fn default() -> Automator {
+        Automator::new()
+    }
+}
+
+impl Automator {
+    pub fn new() -> Automator {
+        Automator {
+            lanes: Vec::new(),
+            last_update: None,
+            last_clock: None,
+        }
+    }
+
+    /// Adds a lane and returns its id. A lane already automating the same parameter of the same
+    /// DSP is replaced.
+    pub fn add(&mut self, lane: AutomationLane) -> usize {
+        let dsp = ffi::FFI::unwrap(&lane.dsp);
+
+        for slot in self.lanes.iter_mut() {
+            if slot.as_ref().map(|l| ffi::FFI::unwrap(&l.dsp) == dsp && l.index == lane.index)
+                   == Some(true) {
+                *slot = None;
+            }
+        }
+        match self.lanes.iter().position(|l| l.is_none()) {
+            Some(id) => {
+                self.lanes[id] = Some(lane);
+                id
+            }
+            None => {
+                self.lanes.push(Some(lane));
+                self.lanes.len() - 1
+            }
+        }
+    }
+
+    pub fn remove(&mut self, id: usize) -> Option<AutomationLane> {
+        match self.lanes.get_mut(id) {
+            Some(slot) => slot.take(),
+            None => None
+        }
+    }
+
+    pub fn get(&self, id: usize) -> Option<&AutomationLane> {
+        self.lanes.get(id).and_then(|l| l.as_ref())
+    }
+
+    /// Returns the number of running lanes.
+    pub fn get_num_lanes(&self) -> usize {
+        self.lanes.iter().filter(|l| l.is_some()).count()
+    }
+
+    pub fn clear(&mut self) {
+        self.lanes.clear();
+    }
+
+    /// Advances every lane with the time elapsed since the previous call.
+    pub fn update(&mut self) -> ::Status {
+        let now = Instant::now();
+        let elapsed = match self.last_update {
+            Some(last) => now.duration_since(last),
+            None => Duration::from_millis(0),
+        };
+
+        self.last_update = Some(now);
+        self.update_by(elapsed)
+    }
+
+    /// Advances every lane with the samples mixed since the previous call. See
+    /// [`AutomationLane::update_clock`](struct.AutomationLane.html#method.update_clock).
+    pub fn update_clock(&mut self, sys: &Sys) -> ::Status {
+        let (clock, sample_rate) = match get_clock(sys) {
+            Ok(c) => c,
+            Err(e) => return e
+        };
+        let elapsed = match self.last_clock {
+            Some(last) => samples_to_duration(clock.saturating_sub(last), sample_rate),
+            None => Duration::from_millis(0)
+        };
+
+        self.last_clock = Some(clock);
+        self.update_by(elapsed)
+    }
+
+    /// Advances every lane as if `elapsed` had passed since the previous update. Every lane is
+    /// updated even if one fails; the first error is returned.
+    pub fn update_by(&mut self, elapsed: Duration) -> ::Status {
+        let mut status = ::Status::Ok;
+
+        for slot in self.lanes.iter_mut() {
+            let finished = match *slot {
+                Some(ref mut lane) => {
+                    let s = lane.update_by(elapsed);
+
+                    if status == ::Status::Ok {
+                        status = s;
+                    }
+                    lane.is_finished()
+                }
+                None => false
+            };
+
+            if finished {
+                *slot = None;
+            }
+        }
+        status
+    }
+}
@@ -0,0 +1,619 @@
+/*
+* Rust-FMOD - Copyright (c) 2018 Gomez Guillaume.
+*
+* The Original software, FmodEx library, is provided by FIRELIGHT TECHNOLOGIES.
+*
+* This software is provided 'as-is', without any express or implied warranty.
+* In no event will the authors be held liable for any damages arising from
+* the use of this software.
+*
+* Permission is granted to anyone to use this software for any purpose,
+* including commercial applications, and to alter it and redistribute it
+* freely, subject to the following restrictions:
+*
+* 1. The origin of this software must not be misrepresented; you must not claim
+*    that you wrote the original software. If you use this software in a product,
+*    an acknowledgment in the product documentation would be appreciated but is
+*    not required.
+*
+* 2. Altered source versions must be plainly marked as such, and must not be
+*    misrepresented as being the original software.
+*
+* 3. This notice may not be removed or altered from any source distribution.
+*/
+
+use ffi;
+use dsp::Dsp;
+use channel::Channel;
+use channel_group::ChannelGroup;
+use fmod_sys::Sys;
+use dsp_preset::{self, DspChainPreset, DspSendPreset};
+use std::collections::{HashMap, HashSet};
+
+/// Maximum number of DSP units between the system DSP head and the end of any branch.
+pub const MAX_DSP_DEPTH: usize = 128;
+
+fn key(dsp: &Dsp) -> usize {
+    ffi::FFI::unwrap(dsp) as usize
+}
+
+fn copy(dsp: &Dsp) -> Dsp {
+    ffi::FFI::wrap(ffi::FFI::unwrap(dsp))
+}
+
+/// Returns the number of units on the longest path starting at `dsp`, following its inputs if
+/// `upstream` is true and its outputs otherwise. Fails with `Status::DSPConnection` if a unit of
+/// `excluded` or a cycle is found on the way.
+fn longest_path(dsp: &Dsp, upstream: bool, excluded: &HashSet<usize>,
+                memo: &mut HashMap<usize, usize>,
+                stack: &mut HashSet<usize>) -> Result<usize, ::Status> {
+    let k = key(dsp);
+
+    if let Some(length) = memo.get(&k) {
+        return Ok(*length);
+    }
+    if excluded.contains(&k) || !stack.insert(k) {
+        return Err(::Status::DSPConnection);
+    }
+    let count = if upstream { dsp.get_num_inputs() } else { dsp.get_num_outputs() };
+    let mut longest = 0;
+
+    for index in 0..count.unwrap_or(0) {
+        let next = match if upstream { dsp.get_input(index) } else { dsp.get_output(index) } {
+            Ok((d, _)) => d,
+            Err(e) => return Err(e)
+        };
+
+        match longest_path(&next, upstream, excluded, memo, stack) {
+            Ok(length) => longest = ::std::cmp::max(longest, length),
+            Err(e) => return Err(e)
+        }
+    }
+    stack.remove(&k);
+    memo.insert(k, longest + 1);
+    Ok(longest + 1)
+}
+
+/// An ordered chain of effects and a set of parallel sends attached to the DSP head of a
+/// [`Channel`](struct.Channel.html), a [`ChannelGroup`](struct.ChannelGroup.html) or the system.
+///
+/// The signal which used to reach the head goes through the effects in order, the first one being
+/// the closest to the head. Each send is fed by the same signal and mixed into the head in
+/// parallel of the effects.
+///
+/// Unlike [`Dsp::add_input`](struct.Dsp.html#method.add_input), which fails once the network is
+/// already broken, every change is checked for cycles and against
+/// [`MAX_DSP_DEPTH`](constant.MAX_DSP_DEPTH.html) before anything is connected. The network is
+/// then rebuilt between [`Sys::lock_DSP`](struct.Sys.html#method.lock_DSP) and
+/// [`Sys::unlock_DSP`](struct.Sys.html#method.unlock_DSP).
+///
+/// ```ignore
+/// let mut chain = DspChain::for_channel_group(&group).unwrap()
+///                     .effect(sys.create_DSP_by_type(rfmod::DspType::LowPass).unwrap())
+///                     .effect(sys.create_DSP_by_type(rfmod::DspType::Echo).unwrap())
+///                     .send(sys.create_DSP_by_type(rfmod::DspType::SFXReverb).unwrap(), 0.3);
+///
+/// chain.build();
+/// chain.move_effect(1, 0);
+/// ```
+///
+/// The units feeding the head are found when the chain is built. A unit connected to the head
+/// afterwards, such as a channel played in the group, skips the effects and the sends until
+/// [`build`](#method.build) is called again.
+///
+/// The chain keeps its units alive. When it is dropped, they are disconnected and the head is fed
+/// directly again.
+pub struct DspChain {
+    sys: Sys,
+    head: Dsp,
+    effects: Vec<Dsp>,
+    sends: Vec<(Dsp, f32)>,
+    /// Units connected by the last rebuild.
+    connected: Vec<Dsp>,
+    built: bool,
+}
+
+impl DspChain {
+    fn new(head: Dsp) -> Result<DspChain, ::Status> {
+        match head.get_system_object() {
+            Ok(sys) => Ok(DspChain {
+                sys: sys,
+                head: head,
+                effects: Vec::new(),
+                sends: Vec::new(),
+                connected: Vec::new(),
+                built: false,
+            }),
+            Err(e) => Err(e)
+        }
+    }
+
+    pub fn for_channel(channel: &Channel) -> Result<DspChain, ::Status> {
+        match channel.get_DSP_head() {
+            Ok(head) => DspChain::new(head),
+            Err(e) => Err(e)
+        }
+    }
+
+    pub fn for_channel_group(group: &ChannelGroup) -> Result<DspChain, ::Status> {
+        match group.get_DSP_head() {
+            Ok(head) => DspChain::new(head),
+            Err(e) => Err(e)
+        }
+    }
+
+    pub fn for_system(sys: &Sys) -> Result<DspChain, ::Status> {
+        match sys.get_DSP_head() {
+            Ok(head) => DspChain::new(head),
+            Err(e) => Err(e)
+        }
+    }
+
+    /// Appends an effect at the end of the chain, the farthest from the head. Nothing is
+    /// connected until [`build`](#method.build) is called.
+    pub fn effect(mut self, dsp: Dsp) -> DspChain {
+        self.effects.push(dsp);
+        self
+    }
+
+    /// Adds a parallel send mixed into the head at the given level. Nothing is connected until
+    /// [`build`](#method.build) is called.
+    pub fn send(mut self, dsp: Dsp, mix: f32) -> DspChain {
+        self.sends.push((dsp, mix));
+        self
+    }
+
+    /// Returns `false` until [`build`](#method.build) succeeds, and again after a failed rebuild
+    /// whose previous layout couldn't be restored either: the units are then disconnected and the
+    /// head is fed directly, as before the build.
+    pub fn is_built(&self) -> bool {
+        self.built
+    }
+
+    /// Validates the chain and connects it. Calling it again picks up the units connected to the
+    /// head since the last build.
+    pub fn build(&mut self) -> ::Status {
+        let effects = self.effects.iter().map(key).collect::<Vec<usize>>();
+        let sends = self.sends.iter().map(|s| key(&s.0)).collect::<Vec<usize>>();
+
+        self.apply(&effects, &sends)
+    }
+
+    /// Returns the units which currently feed the chain: the inputs of the head and of the
+    /// connected units which are not part of the chain.
+    fn get_sources(&self) -> Result<Vec<Dsp>, ::Status> {
+        let connected = self.connected.iter().map(key).collect::<HashSet<usize>>();
+        let mut visited = HashSet::new();
+        let mut to_visit = vec![copy(&self.head)];
+        let mut sources = Vec::new();
+
+        while let Some(dsp) = to_visit.pop() {
+            for index in 0..dsp.get_num_inputs().unwrap_or(0) {
+                let input = match dsp.get_input(index) {
+                    Ok((d, _)) => d,
+                    Err(e) => return Err(e)
+                };
+
+                if !visited.insert(key(&input)) {
+                    continue;
+                }
+                if connected.contains(&key(&input)) {
+                    to_visit.push(input);
+                } else {
+                    sources.push(input);
+                }
+            }
+        }
+        Ok(sources)
+    }
+
+    /// Checks that connecting the given units (identified by their pointers) to the head and to
+    /// the sources won't create a cycle nor exceed the depth limit.
+    fn validate(&self, effects: &[usize], sends: &[usize], sources: &[Dsp]) -> ::Status {
+        let mut units = HashSet::new();
+        let head = key(&self.head);
+
+        for unit in effects.iter().chain(sends.iter()) {
+            if *unit == head || !units.insert(*unit) {
+                return ::Status::DSPConnection;
+            }
+        }
+        for unit in self.connected.iter() {
+            units.insert(key(unit));
+        }
+
+        let mut memo = HashMap::new();
+        let mut stack = HashSet::new();
+        let mut upstream = 0;
+
+        units.insert(head);
+        for source in sources.iter() {
+            match longest_path(source, true, &units, &mut memo, &mut stack) {
+                Ok(length) => upstream = ::std::cmp::max(upstream, length),
+                Err(e) => return e
+            }
+        }
+        units.remove(&head);
+
+        let downstream = match longest_path(&self.head, false, &units, &mut HashMap::new(),
+                                            &mut HashSet::new()) {
+            Ok(length) => length,
+            Err(e) => return e
+        };
+        let chain = if effects.is_empty() && !sends.is_empty() {
+            1
+        } else {
+            effects.len()
+        };
+
+        if downstream + chain + upstream > MAX_DSP_DEPTH {
+            ::Status::DSPTooManyConnections
+        } else {
+            ::Status::Ok
+        }
+    }
+
+    fn apply(&mut self, effects: &[usize], sends: &[usize]) -> ::Status {
+        match self.check(effects, sends) {
+            Ok(sources) => self.rebuild(sources),
+            Err(e) => e
+        }
+    }
+
+    fn check(&self, effects: &[usize], sends: &[usize]) -> Result<Vec<Dsp>, ::Status> {
+        let sources = self.get_sources()?;
+
+        match self.validate(effects, sends, &sources) {
+            ::Status::Ok => Ok(sources),
+            e => Err(e)
+        }
+    }
+
+    fn rebuild(&mut self, sources: Vec<Dsp>) -> ::Status {
+        match self.sys.lock_DSP() {
+            ::Status::Ok => {}
+            e => return e
+        }
+        let status = match self.connect(&sources) {
+            ::Status::Ok => {
+                self.built = true;
+                ::Status::Ok
+            }
+            e => {
+                // never leave the network half-connected
+                self.built = false;
+                self.unlink(&sources);
+                e
+            }
+        };
+
+        match self.sys.unlock_DSP() {
+            ::Status::Ok => status,
+            e => e
+        }
+    }
+
+    fn disconnect(&mut self, sources: &[Dsp]) -> ::Status {
+        for unit in self.connected.drain(..) {
+            match unit.disconnect_all(true, true) {
+                ::Status::Ok => {}
+                e => return e
+            }
+        }
+        for unit in self.effects.iter().chain(self.sends.iter().map(|s| &s.0)) {
+            match unit.disconnect_all(true, true) {
+                ::Status::Ok => {}
+                e => return e
+            }
+        }
+        for source in sources.iter() {
+            // the source may not be connected to the head anymore, which is fine
+            self.head.disconnect_from(copy(source));
+        }
+        ::Status::Ok
+    }
+
+    /// Disconnects every unit of the chain and feeds the head directly from the sources again.
+    fn unlink(&mut self, sources: &[Dsp]) -> ::Status {
+        match self.disconnect(sources) {
+            ::Status::Ok => {}
+            e => return e
+        }
+        for source in sources.iter() {
+            if let Err(e) = self.head.add_input(copy(source)) {
+                return e;
+            }
+        }
+        ::Status::Ok
+    }
+
+    fn connect(&mut self, sources: &[Dsp]) -> ::Status {
+        match self.disconnect(sources) {
+            ::Status::Ok => {}
+            e => return e
+        }
+
+        let mut tail = copy(&self.head);
+
+        for effect in self.effects.iter() {
+            if let Err(e) = tail.add_input(copy(effect)) {
+                return e;
+            }
+            self.connected.push(copy(effect));
+            tail = copy(effect);
+        }
+        for source in sources.iter() {
+            if let Err(e) = tail.add_input(copy(source)) {
+                return e;
+            }
+        }
+        for &(ref send, mix) in self.sends.iter() {
+            match self.head.add_input(copy(send)) {
+                Ok(connection) => match connection.set_mix(mix) {
+                    ::Status::Ok => {}
+                    e => return e
+                },
+                Err(e) => return e
+            }
+            self.connected.push(copy(send));
+            for source in sources.iter() {
+                if let Err(e) = send.add_input(copy(source)) {
+                    return e;
+                }
+            }
+        }
+        ::Status::Ok
+    }
+
+    fn refresh(&mut self) -> ::Status {
+        if self.is_built() {
+            self.build()
+        } else {
+            ::Status::Ok
+        }
+    }
+
+    /// Reconnects the previous layout after a failed change has been undone, if the failure
+    /// left the chain disconnected. The status of the failed change is returned.
+    fn restore(&mut self, was_built: bool, status: ::Status) -> ::Status {
+        if was_built && !self.is_built() {
+            self.build();
+        }
+        status
+    }
+
+    fn planned_sends(&self) -> Vec<usize> {
+        self.sends.iter().map(|s| key(&s.0)).collect()
+    }
+
+    /// Inserts an effect at the given position, 0 being the closest to the head. If it can't be
+    /// connected, the effect is given back with the error.
+    #[allow(clippy::result_large_err)]
+    pub fn insert(&mut self, index: usize, dsp: Dsp) -> Result<(), (Dsp, ::Status)> {
+        if index > self.effects.len() {
+            return Err((dsp, ::Status::InvalidParam));
+        }
+        let mut effects = self.effects.iter().map(key).collect::<Vec<usize>>();
+
+        effects.insert(index, key(&dsp));
+        if self.is_built() {
+            let sends = self.planned_sends();
+
+            match self.check(&effects, &sends) {
+                Ok(sources) => {
+                    self.effects.insert(index, dsp);
+                    match self.rebuild(sources) {
+                        ::Status::Ok => Ok(()),
+                        e => {
+                            let dsp = self.effects.remove(index);
+
+                            Err((dsp, self.restore(true, e)))
+                        }
+                    }
+                }
+                Err(e) => Err((dsp, e))
+            }
+        } else {
+            self.effects.insert(index, dsp);
+            Ok(())
+        }
+    }
+
+    /// Removes an effect from the chain and gives it back, disconnected.
+    pub fn remove(&mut self, index: usize) -> Result<Dsp, ::Status> {
+        if index >= self.effects.len() {
+            return Err(::Status::InvalidParam);
+        }
+        let was_built = self.is_built();
+        let dsp = self.effects.remove(index);
+
+        match self.refresh() {
+            ::Status::Ok => Ok(dsp),
+            e => {
+                self.effects.insert(index, dsp);
+                Err(self.restore(was_built, e))
+            }
+        }
+    }
+
+    /// Moves an effect from one position to another. If the new order can't be connected, the
+    /// previous one is kept.
+    pub fn move_effect(&mut self, from: usize, to: usize) -> ::Status {
+        if from >= self.effects.len() || to >= self.effects.len() {
+            return ::Status::InvalidParam;
+        }
+        if from == to {
+            return ::Status::Ok;
+        }
+        let was_built = self.is_built();
+        let dsp = self.effects.remove(from);
+
+        self.effects.insert(to, dsp);
+        match self.refresh() {
+            ::Status::Ok => ::Status::Ok,
+            e => {
+                let dsp = self.effects.remove(to);
+
+                self.effects.insert(from, dsp);
+                self.restore(was_built, e)
+            }
+        }
+    }
+
+    /// Bypasses an effect without changing the connections.
+    pub fn set_bypass(&self, index: usize, bypass: bool) -> ::Status {
+        match self.effects.get(index) {
+            Some(dsp) => dsp.set_bypass(bypass),
+            None => ::Status::InvalidParam
+        }
+    }
+
+    pub fn get_effects(&self) -> &[Dsp] {
+        &self.effects
+    }
+
+    /// Adds a parallel send and returns its index. If it can't be connected, the send is given
+    /// back with the error.
+    #[allow(clippy::result_large_err)]
+    pub fn add_send(&mut self, dsp: Dsp, mix: f32) -> Result<usize, (Dsp, ::Status)> {
+        if self.is_built() {
+            let effects = self.effects.iter().map(key).collect::<Vec<usize>>();
+            let mut sends = self.planned_sends();
+
+            sends.push(key(&dsp));
+            match self.check(&effects, &sends) {
+                Ok(sources) => {
+                    self.sends.push((dsp, mix));
+                    match self.rebuild(sources) {
+                        ::Status::Ok => {}
+                        e => {
+                            let (dsp, _) = self.sends.pop().unwrap();
+
+                            return Err((dsp, self.restore(true, e)));
+                        }
+                    }
+                }
+                Err(e) => return Err((dsp, e))
+            }
+        } else {
+            self.sends.push((dsp, mix));
+        }
+        Ok(self.sends.len() - 1)
+    }
+
+    /// Removes a send and gives it back, disconnected.
+    pub fn remove_send(&mut self, index: usize) -> Result<Dsp, ::Status> {
+        if index >= self.sends.len() {
+            return Err(::Status::InvalidParam);
+        }
+        let was_built = self.is_built();
+        let (dsp, mix) = self.sends.remove(index);
+
+        match self.refresh() {
+            ::Status::Ok => Ok(dsp),
+            e => {
+                self.sends.insert(index, (dsp, mix));
+                Err(self.restore(was_built, e))
+            }
+        }
+    }
+
+    /// Changes the level at which a send is mixed into the head.
+    pub fn set_send_mix(&mut self, index: usize, mix: f32) -> ::Status {
+        if index >= self.sends.len() {
+            return ::Status::InvalidParam;
+        }
+        self.sends[index].1 = mix;
+        if !self.is_built() {
+            return ::Status::Ok;
+        }
+        let send = key(&self.sends[index].0);
+
+        for input in 0..self.head.get_num_inputs().unwrap_or(0) {
+            match self.head.get_input(input) {
+                Ok((dsp, connection)) => if key(&dsp) == send {
+                    return connection.set_mix(mix);
+                },
+                Err(e) => return e
+            }
+        }
+        ::Status::DSPNotFound
+    }
+
+    /// Returns the sends and their mix levels.
+    pub fn get_sends(&self) -> &[(Dsp, f32)] {
+        &self.sends
+    }
+
+    /// Saves the parameters of every effect and send.
+    pub fn to_preset(&self) -> Result<DspChainPreset, ::Status> {
+        let mut preset = DspChainPreset::from_effects(&self.effects)?;
+
+        for &(ref send, mix) in self.sends.iter() {
+            match send.to_preset() {
+                Ok(effect) => preset.sends.push(DspSendPreset { effect: effect, mix: mix }),
+                Err(e) => return Err(e)
+            }
+        }
+        Ok(preset)
+    }
+
+    /// Applies a preset saved from a chain with the same effects and sends.
+    pub fn apply_preset(&mut self, preset: &DspChainPreset) -> ::Status {
+        if preset.sends.len() != self.sends.len() {
+            return ::Status::InvalidParam;
+        }
+        // the effects are checked by apply_to_effects, the sends have to be checked beforehand
+        for (index, send) in preset.sends.iter().enumerate() {
+            if let Err(e) = dsp_preset::check_preset(&self.sends[index].0, &send.effect) {
+                return e;
+            }
+        }
+        match preset.apply_to_effects(&self.effects) {
+            ::Status::Ok => {}
+            e => return e
+        }
+        for (index, send) in preset.sends.iter().enumerate() {
+            match self.sends[index].0.apply_preset(&send.effect) {
+                ::Status::Ok => {}
+                e => return e
+            }
+            match self.set_send_mix(index, send.mix) {
+                ::Status::Ok => {}
+                e => return e
+            }
+        }
+        ::Status::Ok
+    }
+
+    /// Creates the effects and sends of a preset and appends them to the chain, like
+    /// [`effect`](#method.effect) and [`send`](#method.send). Only built-in DSPs can be created.
+    pub fn load_preset(mut self, sys: &Sys, preset: &DspChainPreset) -> Result<DspChain, ::Status> {
+        for effect in preset.effects.iter() {
+            match effect.create_DSP(sys) {
+                Ok(dsp) => self.effects.push(dsp),
+                Err(e) => return Err(e)
+            }
+        }
+        for send in preset.sends.iter() {
+            match send.effect.create_DSP(sys) {
+                Ok(dsp) => self.sends.push((dsp, send.mix)),
+                Err(e) => return Err(e)
+            }
+        }
+        Ok(self)
+    }
+}
+
+impl Drop for DspChain {
+    fn drop(&mut self) {
+        if !self.is_built() {
+            return;
+        }
+        if let Ok(sources) = self.get_sources() {
+            self.sys.lock_DSP();
+            self.unlink(&sources);
+            self.sys.unlock_DSP();
+        }
+    }
+}
@@ -25,6 +25,7 @@
 use ffi;
 use types::*;
 use dsp;
+use speaker_matrix::SpeakerMatrix;
 use libc::{c_int, c_void};
 use fmod_sys;
 use fmod_sys::MemoryUsageDetails;
@@ -104,6 +105,81 @@ impl DspConnection {
         }
     }
 
+    /// Sets the levels of every speaker at once. The matrix must match the speaker mode of the
+    /// system and is checked before being sent to FMOD.
+    ///
+    /// Returns `Status::InvalidParam` if the input DSP was created for a fixed number of channels
+    /// different from the input channels of `matrix`.
+    pub fn set_speaker_matrix(&self, matrix: &SpeakerMatrix) -> ::Status {
+        let speaker_mode = match self.get_speaker_mode() {
+            Ok(m) => m,
+            Err(e) => return e
+        };
+        let info = match self.get_input() {
+            Ok(dsp) => match dsp.get_info() {
+                Ok(i) => i,
+                Err(e) => return e
+            },
+            Err(e) => return e
+        };
+
+        // 0 means the DSP processes whatever it receives
+        if info.channels > 0 && info.channels as usize != matrix.get_input_channels() {
+            return ::Status::InvalidParam;
+        }
+        match matrix.validate(speaker_mode, matrix.get_input_channels()) {
+            ::Status::Ok => {}
+            e => return e
+        }
+        for speaker in matrix.get_speakers().iter() {
+            let mut levels = matrix.get_levels(*speaker).unwrap_or(&[]).to_vec();
+
+            match self.set_levels(*speaker, &mut levels) {
+                ::Status::Ok => {}
+                e => return e
+            }
+        }
+        ::Status::Ok
+    }
+
+    /// Reads the levels of every speaker of the system speaker mode. The number of input channels
+    /// comes from the input DSP, or from the software format of the system if the input DSP
+    /// processes whatever it receives.
+    pub fn get_speaker_matrix(&self) -> Result<SpeakerMatrix, ::Status> {
+        let speaker_mode = self.get_speaker_mode()?;
+        let input_channels = self.get_input_channels()?;
+        let mut matrix = SpeakerMatrix::new(speaker_mode, input_channels)?;
+
+        for speaker in matrix.get_speakers().iter() {
+            let levels = self.get_levels(*speaker, input_channels)?;
+
+            matrix.set_levels(*speaker, &levels);
+        }
+        Ok(matrix)
+    }
+
+    fn get_input_channels(&self) -> Result<usize, ::Status> {
+        let dsp = self.get_input()?;
+        match dsp.get_info() {
+            Ok(ref info) if info.channels > 0 => Ok(info.channels as usize),
+            Ok(_) => match dsp.get_system_object() {
+                Ok(s) => s.get_software_format().map(|f| f.num_output_channels as usize),
+                Err(e) => Err(e)
+            },
+            Err(e) => Err(e)
+        }
+    }
+
+    fn get_speaker_mode(&self) -> Result<::SpeakerMode, ::Status> {
+        match self.get_output() {
+            Ok(dsp) => match dsp.get_system_object() {
+                Ok(s) => s.get_speaker_mode(),
+                Err(e) => Err(e)
+            },
+            Err(e) => Err(e)
+        }
+    }
+
     pub fn get_memory_info(&self, MemoryBits(memory_bits): MemoryBits,
                            EventMemoryBits(event_memory_bits): EventMemoryBits)
                            -> Result<(u32, MemoryUsageDetails), ::Status> {
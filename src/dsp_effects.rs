@@ -0,0 +1,264 @@
+/*
+* Rust-FMOD - Copyright (c) 2018 Gomez Guillaume.
+*
+* The Original software, FmodEx library, is provided by FIRELIGHT TECHNOLOGIES.
+*
+* This software is provided 'as-is', without any express or implied warranty.
+* In no event will the authors be held liable for any damages arising from
+* the use of this software.
+*
+* Permission is granted to anyone to use this software for any purpose,
+* including commercial applications, and to alter it and redistribute it
+* freely, subject to the following restrictions:
+*
+* 1. The origin of this software must not be misrepresented; you must not claim
+*    that you wrote the original software. If you use this software in a product,
+*    an acknowledgment in the product documentation would be appreciated but is
+*    not required.
+*
+* 2. Altered source versions must be plainly marked as such, and must not be
+*    misrepresented as being the original software.
+*
+* 3. This notice may not be removed or altered from any source distribution.
+*/
+
+use dsp::Dsp;
+use fmod_sys::Sys;
+use std::ops::Deref;
+use std::time::Duration;
+
+fn check_range(value: f32, min: f32, max: f32) -> ::Status {
+    if !value.is_finite() {
+        ::Status::InvalidFloat
+    } else if value < min || value > max {
+        ::Status::InvalidParam
+    } else {
+        ::Status::Ok
+    }
+}
+
+fn get_value(dsp: &Dsp, index: i32) -> Result<f32, ::Status> {
+    match dsp.get_parameter(index, 16) {
+        Ok((value, _)) => Ok(value),
+        Err(::RStatus::FMOD(e)) => Err(e),
+        Err(_) => Err(::Status::Internal)
+    }
+}
+
+fn duration_to_ms(duration: Duration) -> f32 {
+    duration.as_secs() as f32 * 1000f32 + duration.subsec_nanos() as f32 / 1_000_000f32
+}
+
+fn ms_to_duration(ms: f32) -> Duration {
+    let ms = if ms > 0f32 { ms as f64 } else { 0f64 };
+
+    Duration::new((ms / 1000f64) as u64, ((ms % 1000f64) * 1_000_000f64) as u32)
+}
+
+macro_rules! effect_parameter {
+    (f32, $param:expr, $setter:ident, $getter:ident, $min:expr, $max:expr) => {
+        /// Returns `Status::InvalidParam` if the value is out of range.
+        pub fn $setter(&self, value: f32) -> ::Status {
+            match check_range(value, $min, $max) {
+                ::Status::Ok => self.dsp.set_parameter($param as i32, value),
+                e => e
+            }
+        }
+
+        pub fn $getter(&self) -> Result<f32, ::Status> {
+            get_value(&self.dsp, $param as i32)
+        }
+    };
+    (i32, $param:expr, $setter:ident, $getter:ident, $min:expr, $max:expr) => {
+        /// Returns `Status::InvalidParam` if the value is out of range.
+        pub fn $setter(&self, value: i32) -> ::Status {
+            if !($min..=$max).contains(&value) {
+                ::Status::InvalidParam
+            } else {
+                self.dsp.set_parameter($param as i32, value as f32)
+            }
+        }
+
+        pub fn $getter(&self) -> Result<i32, ::Status> {
+            get_value(&self.dsp, $param as i32).map(|v| v.round() as i32)
+        }
+    };
+    (ms, $param:expr, $setter:ident, $getter:ident, $min:expr, $max:expr) => {
+        /// Returns `Status::InvalidParam` if the duration is out of range.
+        pub fn $setter(&self, value: Duration) -> ::Status {
+            let ms = duration_to_ms(value);
+
+            match check_range(ms, $min, $max) {
+                ::Status::Ok => self.dsp.set_parameter($param as i32, ms),
+                e => e
+            }
+        }
+
+        pub fn $getter(&self) -> Result<Duration, ::Status> {
+            get_value(&self.dsp, $param as i32).map(ms_to_duration)
+        }
+    };
+}
+
+macro_rules! dsp_effect {
+    ($(#[$attr:meta])* pub struct $name:ident($dsp_type:expr) {
+        $($kind:ident $param:expr => $setter:ident, $getter:ident, $min:expr, $max:expr;)*
+    }) => {
+        $(#[$attr])*
+        pub struct $name {
+            dsp: Dsp,
+        }
+
+        impl $name {
+            pub fn new(sys: &Sys) -> Result<$name, ::Status> {
+                sys.create_DSP_by_type($dsp_type).map(|dsp| $name { dsp: dsp })
+            }
+
+            /// Wraps an existing DSP. The DSP is given back if it isn't of the right type.
+            #[allow(clippy::result_large_err)]
+            pub fn from_dsp(dsp: Dsp) -> Result<$name, Dsp> {
+                match dsp.get_type() {
+                    Ok(t) if t == $dsp_type => Ok($name { dsp: dsp }),
+                    _ => Err(dsp)
+                }
+            }
+
+            pub fn into_dsp(self) -> Dsp {
+                self.dsp
+            }
+
+            $(effect_parameter!($kind, $param, $setter, $getter, $min, $max);)*
+        }
+
+        impl Deref for $name {
+            type Target = Dsp;
+
+            fn deref(&self) -> &Dsp {
+                &self.dsp
+            }
+        }
+    }
+}
+
+dsp_effect!(
+    /// Resonant lowpass filter, see [`DspLowPass`](enums/fmod/type.DspLowPass.html).
+    pub struct LowPass(::DspType::LowPass) {
+        f32 ::DspLowPass::Cutoff => set_cutoff_hz, get_cutoff_hz, 10f32, 22000f32;
+        f32 ::DspLowPass::Resonance => set_resonance, get_resonance, 1f32, 10f32;
+    }
+);
+
+dsp_effect!(
+    /// Echo filter, see [`DspTypeEcho`](enums/fmod/type.DspTypeEcho.html).
+    pub struct Echo(::DspType::Echo) {
+        ms ::DspTypeEcho::Delay => set_delay, get_delay, 10f32, 5000f32;
+        f32 ::DspTypeEcho::DecayRatio => set_decay_ratio, get_decay_ratio, 0f32, 1f32;
+        i32 ::DspTypeEcho::MaxChannels => set_max_channels, get_max_channels, 0, 16;
+        f32 ::DspTypeEcho::DryMix => set_dry_mix, get_dry_mix, 0f32, 1f32;
+        f32 ::DspTypeEcho::WetMix => set_wet_mix, get_wet_mix, 0f32, 1f32;
+    }
+);
+
+dsp_effect!(
+    /// Limiter, see [`DspCompressor`](enums/fmod/type.DspCompressor.html).
+    pub struct Compressor(::DspType::Compressor) {
+        f32 ::DspCompressor::Threshold => set_threshold_db, get_threshold_db, -60f32, 0f32;
+        ms ::DspCompressor::Attack => set_attack, get_attack, 10f32, 200f32;
+        ms ::DspCompressor::Release => set_release_time, get_release_time, 20f32, 1000f32;
+        f32 ::DspCompressor::GainMakeup => set_gain_makeup_db, get_gain_makeup_db, 0f32, 30f32;
+    }
+);
+
+dsp_effect!(
+    /// I3DL2 reverb, see [`DspSfxReverb`](enums/fmod/type.DspSfxReverb.html). Levels are in mB.
+    pub struct SfxReverb(::DspType::SFXReverb) {
+        f32 ::DspSfxReverb::DryLevel => set_dry_level, get_dry_level, -10000f32, 0f32;
+        f32 ::DspSfxReverb::Room => set_room, get_room, -10000f32, 0f32;
+        f32 ::DspSfxReverb::RoomHF => set_room_hf, get_room_hf, -10000f32, 0f32;
+        f32 ::DspSfxReverb::DecayTime => set_decay_time, get_decay_time, 0.1f32, 20f32;
+        f32 ::DspSfxReverb::DecayHFRatio => set_decay_hf_ratio, get_decay_hf_ratio, 0.1f32, 2f32;
+        f32 ::DspSfxReverb::ReflectionsLevel => set_reflections_level, get_reflections_level,
+            -10000f32, 1000f32;
+        f32 ::DspSfxReverb::ReflectionsDelay => set_reflections_delay, get_reflections_delay,
+            0f32, 0.3f32;
+        f32 ::DspSfxReverb::ReverbLevel => set_reverb_level, get_reverb_level, -10000f32, 2000f32;
+        f32 ::DspSfxReverb::ReverbDelay => set_reverb_delay, get_reverb_delay, 0f32, 0.1f32;
+        f32 ::DspSfxReverb::Diffusion => set_diffusion, get_diffusion, 0f32, 100f32;
+        f32 ::DspSfxReverb::Density => set_density, get_density, 0f32, 100f32;
+        f32 ::DspSfxReverb::HFReference => set_hf_reference, get_hf_reference, 20f32, 20000f32;
+        f32 ::DspSfxReverb::RoomLF => set_room_lf, get_room_lf, -10000f32, 0f32;
+        f32 ::DspSfxReverb::LFReference => set_lf_reference, get_lf_reference, 20f32, 1000f32;
+    }
+);
+
+dsp_effect!(
+    /// Parametric equalizer band, see [`DspTypeParameq`](enums/fmod/type.DspTypeParameq.html).
+    pub struct ParamEq(::DspType::Parameq) {
+        f32 ::DspTypeParameq::Center => set_center_hz, get_center_hz, 20f32, 22000f32;
+        f32 ::DspTypeParameq::Bandwidth => set_bandwidth, get_bandwidth, 0.2f32, 5f32;
+        f32 ::DspTypeParameq::Gain => set_gain, get_gain, 0.05f32, 3f32;
+    }
+);
+
+dsp_effect!(
+    /// Pitch shifter, see [`DspPitchShift`](enums/fmod/type.DspPitchShift.html).
+    pub struct PitchShift(::DspType::PitchShift) {
+        f32 ::DspPitchShift::Pitch => set_pitch, get_pitch, 0.5f32, 2f32;
+        i32 ::DspPitchShift::MaxChannels => set_max_channels, get_max_channels, 0, 16;
+    }
+);
+
+impl PitchShift {
+    /// Sets the FFT window size: 256, 512, 1024, 2048 or 4096.
+    pub fn set_fft_size(&self, size: u32) -> ::Status {
+        match size {
+            256 | 512 | 1024 | 2048 | 4096 => {
+                self.dsp.set_parameter(::DspPitchShift::FFTSize as i32, size as f32)
+            }
+            _ => ::Status::InvalidParam
+        }
+    }
+
+    pub fn get_fft_size(&self) -> Result<u32, ::Status> {
+        get_value(&self.dsp, ::DspPitchShift::FFTSize as i32).map(|v| v as u32)
+    }
+}
+
+dsp_effect!(
+    /// Chorus, see [`DspChorus`](enums/fmod/type.DspChorus.html).
+    pub struct Chorus(::DspType::Chorus) {
+        f32 ::DspChorus::DryMix => set_dry_mix, get_dry_mix, 0f32, 1f32;
+        f32 ::DspChorus::WetMix1 => set_wet_mix1, get_wet_mix1, 0f32, 1f32;
+        f32 ::DspChorus::WetMix2 => set_wet_mix2, get_wet_mix2, 0f32, 1f32;
+        f32 ::DspChorus::WetMix3 => set_wet_mix3, get_wet_mix3, 0f32, 1f32;
+        ms ::DspChorus::Delay => set_delay, get_delay, 0.1f32, 100f32;
+        f32 ::DspChorus::Rate => set_rate_hz, get_rate_hz, 0f32, 20f32;
+        f32 ::DspChorus::Depth => set_depth, get_depth, 0f32, 1f32;
+    }
+);
+
+dsp_effect!(
+    /// Flange, see [`DspFlange`](enums/fmod/type.DspFlange.html).
+    pub struct Flange(::DspType::Flange) {
+        f32 ::DspFlange::DryMix => set_dry_mix, get_dry_mix, 0f32, 1f32;
+        f32 ::DspFlange::WetMix => set_wet_mix, get_wet_mix, 0f32, 1f32;
+        f32 ::DspFlange::Depth => set_depth, get_depth, 0.01f32, 1f32;
+        f32 ::DspFlange::Rate => set_rate_hz, get_rate_hz, 0f32, 20f32;
+    }
+);
+
+dsp_effect!(
+    /// Distortion, see [`DspDistortion`](enums/fmod/type.DspDistortion.html).
+    pub struct Distortion(::DspType::Distortion) {
+        f32 ::DspDistortion::Level => set_level, get_level, 0f32, 1f32;
+    }
+);
+
+dsp_effect!(
+    /// Volume normalizer, see [`DspNormalize`](enums/fmod/type.DspNormalize.html).
+    pub struct Normalize(::DspType::Normalize) {
+        ms ::DspNormalize::FadeTime => set_fade_time, get_fade_time, 0f32, 20000f32;
+        f32 ::DspNormalize::Threshold => set_threshold, get_threshold, 0f32, 1f32;
+        f32 ::DspNormalize::MaxAmp => set_max_amp, get_max_amp, 1f32, 100000f32;
+    }
+);
@@ -0,0 +1,222 @@
+/*
+* Rust-FMOD - Copyright (c) 2018 Gomez Guillaume.
+*
+* The Original software, FmodEx library, is provided by FIRELIGHT TECHNOLOGIES.
+*
+* This software is provided 'as-is', without any express or implied warranty.
+* In no event will the authors be held liable for any damages arising from
+* the use of this software.
+*
+* Permission is granted to anyone to use this software for any purpose,
+* including commercial applications, and to alter it and redistribute it
+* freely, subject to the following restrictions:
+*
+* 1. The origin of this software must not be misrepresented; you must not claim
+*    that you wrote the original software. If you use this software in a product,
+*    an acknowledgment in the product documentation would be appreciated but is
+*    not required.
+*
+* 2. Altered source versions must be plainly marked as such, and must not be
+*    misrepresented as being the original software.
+*
+* 3. This notice may not be removed or altered from any source distribution.
+*/
+
+use ffi;
+use dsp::Dsp;
+use fmod_sys::Sys;
+use speaker_matrix;
+use utils::{escape_string, json_float};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+#[derive(Debug, PartialEq, Clone)]
+/// Parameter of a [`DspNode`](struct.DspNode.html).
+pub struct DspNodeParameter {
+    pub name: String,
+    pub value: f32,
+    /// Value as formatted by FMOD.
+    pub display: String,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+/// A DSP unit of a [`DspGraph`](struct.DspGraph.html).
+pub struct DspNode {
+    /// Index of the node in [`DspGraph::nodes`](struct.DspGraph.html#structfield.nodes).
+    pub id: usize,
+    pub name: String,
+    pub dsp_type: ::DspType,
+    pub active: bool,
+    pub bypass: bool,
+    pub parameters: Vec<DspNodeParameter>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+/// A connection of a [`DspGraph`](struct.DspGraph.html), in the direction of the signal.
+pub struct DspEdge {
+    /// Id of the DSP whose output is used.
+    pub from: usize,
+    /// Id of the DSP reading the signal.
+    pub to: usize,
+    pub mix: f32,
+    /// Levels of each speaker of the system speaker mode, one per input channel. Trailing zeros
+    /// are removed.
+    pub levels: Vec<(::Speaker, Vec<f32>)>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+/// Snapshot of the DSP network, returned by
+/// [`Sys::dsp_graph`](struct.Sys.html#method.dsp_graph). The first node is the DSP head.
+pub struct DspGraph {
+    pub nodes: Vec<DspNode>,
+    pub edges: Vec<DspEdge>,
+}
+
+fn create_node(dsp: &Dsp, id: usize) -> DspNode {
+    let mut parameters = Vec::new();
+
+    for index in 0..dsp.get_num_parameters().unwrap_or(0) {
+        let (value, display) = match dsp.get_parameter(index, 16) {
+            Ok((v, d)) => (v, d.trim_end_matches('\0').to_owned()),
+            Err(_) => (0f32, String::new())
+        };
+
+        parameters.push(DspNodeParameter {
+            name: dsp.get_parameter_info(index).map(|i| i.name).unwrap_or_default(),
+            value: value,
+            display: display,
+        });
+    }
+    DspNode {
+        id: id,
+        name: dsp.get_info().map(|i| i.name).unwrap_or_default(),
+        dsp_type: dsp.get_type().unwrap_or(::DspType::Unknown),
+        active: dsp.get_active().unwrap_or(false),
+        bypass: dsp.get_bypass().unwrap_or(false),
+        parameters: parameters,
+    }
+}
+
+pub fn from_head(sys: &Sys, head: Dsp) -> DspGraph {
+    let speakers = match sys.get_speaker_mode() {
+        Ok(mode) => speaker_matrix::get_speakers(mode).unwrap_or(&[]),
+        Err(_) => &[]
+    };
+    let mut graph = DspGraph {
+        nodes: vec![create_node(&head, 0)],
+        edges: Vec::new(),
+    };
+    let mut ids = HashMap::new();
+    let mut to_visit = vec![(head, 0)];
+
+    ids.insert(ffi::FFI::unwrap(&to_visit[0].0) as usize, 0);
+    while let Some((dsp, id)) = to_visit.pop() {
+        for index in 0..dsp.get_num_inputs().unwrap_or(0) {
+            let (input, connection) = match dsp.get_input(index) {
+                Ok(i) => i,
+                Err(_) => continue
+            };
+            let key = ffi::FFI::unwrap(&input) as usize;
+            let input_id = match ids.get(&key) {
+                Some(input_id) => *input_id,
+                None => {
+                    let input_id = graph.nodes.len();
+
+                    ids.insert(key, input_id);
+                    graph.nodes.push(create_node(&input, input_id));
+                    to_visit.push((ffi::FFI::wrap(ffi::FFI::unwrap(&input)), input_id));
+                    input_id
+                }
+            };
+            let mut levels = Vec::with_capacity(speakers.len());
+
+            for speaker in speakers.iter() {
+                if let Ok(mut l) = connection.get_levels(*speaker,
+                                                         speaker_matrix::MAX_INPUT_CHANNELS) {
+                    while l.last() == Some(&0f32) {
+                        l.pop();
+                    }
+                    levels.push((*speaker, l));
+                }
+            }
+            graph.edges.push(DspEdge {
+                from: input_id,
+                to: id,
+                mix: connection.get_mix().unwrap_or(0f32),
+                levels: levels,
+            });
+        }
+    }
+    graph
+}
+
+impl DspGraph {
+    /// Returns the graph in the Graphviz DOT format. Bypassed units are drawn dashed and inactive
+    /// ones grayed out.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph dsp {\n    rankdir=LR;\n    node [shape=box];\n");
+
+        for node in self.nodes.iter() {
+            let mut label = format!("{}\\n{:?}", escape_string(&node.name), node.dsp_type);
+
+            for parameter in node.parameters.iter() {
+                let _ = write!(label, "\\n{} = {}", escape_string(&parameter.name),
+                               escape_string(&parameter.display));
+            }
+            let style = match (node.active, node.bypass) {
+                (false, _) => ", style=filled, fillcolor=lightgray",
+                (true, true) => ", style=dashed",
+                _ => ""
+            };
+            let _ = writeln!(out, "    n{} [label=\"{}\"{}];", node.id, label, style);
+        }
+        for edge in self.edges.iter() {
+            let _ = writeln!(out, "    n{} -> n{} [label=\"{:.2}\"];", edge.from, edge.to, edge.mix);
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// Returns the graph as JSON.
+    pub fn to_json(&self) -> String {
+        let mut out = String::from("{\"nodes\":[");
+
+        for (pos, node) in self.nodes.iter().enumerate() {
+            if pos > 0 {
+                out.push(',');
+            }
+            let _ = write!(out, "{{\"id\":{},\"name\":\"{}\",\"type\":\"{:?}\",\"active\":{},\
+                                 \"bypass\":{},\"parameters\":[",
+                           node.id, escape_string(&node.name), node.dsp_type, node.active,
+                           node.bypass);
+            for (pos, parameter) in node.parameters.iter().enumerate() {
+                if pos > 0 {
+                    out.push(',');
+                }
+                let _ = write!(out, "{{\"name\":\"{}\",\"value\":{},\"display\":\"{}\"}}",
+                               escape_string(&parameter.name), json_float(parameter.value),
+                               escape_string(&parameter.display));
+            }
+            out.push_str("]}");
+        }
+        out.push_str("],\"edges\":[");
+        for (pos, edge) in self.edges.iter().enumerate() {
+            if pos > 0 {
+                out.push(',');
+            }
+            let _ = write!(out, "{{\"from\":{},\"to\":{},\"mix\":{},\"levels\":{{",
+                           edge.from, edge.to, json_float(edge.mix));
+            for (pos, &(speaker, ref levels)) in edge.levels.iter().enumerate() {
+                let levels : Vec<String> = levels.iter().map(|l| json_float(*l)).collect();
+
+                if pos > 0 {
+                    out.push(',');
+                }
+                let _ = write!(out, "\"{:?}\":[{}]", speaker, levels.join(","));
+            }
+            out.push_str("}}");
+        }
+        out.push_str("]}");
+        out
+    }
+}
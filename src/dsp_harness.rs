@@ -0,0 +1,644 @@
+/*
+* Rust-FMOD - Copyright (c) 2018 Gomez Guillaume.
+*
+* The Original software, FmodEx library, is provided by FIRELIGHT TECHNOLOGIES.
+*
+* This software is provided 'as-is', without any express or implied warranty.
+* In no event will the authors be held liable for any damages arising from
+* the use of this software.
+*
+* Permission is granted to anyone to use this software for any purpose,
+* including commercial applications, and to alter it and redistribute it
+* freely, subject to the following restrictions:
+*
+* 1. The origin of this software must not be misrepresented; you must not claim
+*    that you wrote the original software. If you use this software in a product,
+*    an acknowledgment in the product documentation would be appreciated but is
+*    not required.
+*
+* 2. Altered source versions must be plainly marked as such, and must not be
+*    misrepresented as being the original software.
+*
+* 3. This notice may not be removed or altered from any source distribution.
+*/
+
+use ffi;
+use dsp;
+use dsp::DspProcessor;
+use callbacks::DspReadCallback;
+use std::f32::consts::PI;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+/// Test signals generated by a [`DspHarness`](struct.DspHarness.html). Every channel receives the
+/// same signal.
+pub enum TestSignal {
+    /// A single sample at 1.0 followed by silence.
+    Impulse,
+    Sine {
+        frequency: f32,
+        amplitude: f32,
+    },
+    /// Uniform white noise between -amplitude and amplitude. The same seed always gives the same
+    /// noise.
+    Noise {
+        amplitude: f32,
+        seed: u32,
+    },
+    Silence,
+}
+
+/// Interleaved samples produced or consumed by a [`DspHarness`](struct.DspHarness.html).
+#[derive(Debug, PartialEq, Clone)]
+pub struct TestBuffer {
+    pub samples: Vec<f32>,
+    pub channels: usize,
+    pub sample_rate: u32,
+}
+
+/// Drives a [`DspProcessor`](trait.DspProcessor.html) or a
+/// [`DspReadCallback`](callbacks/type.DspReadCallback.html) with generated buffers, block by
+/// block, as the FMOD mixer would. Nothing here calls FMOD, so it can be used from unit tests
+/// without a sound card.
+///
+/// ```ignore
+/// let harness = DspHarness::new(44100, 2, 2, 256);
+/// let input = harness.generate(TestSignal::Sine { frequency: 1000f32, amplitude: 0.5 }, 4096);
+/// let output = harness.run_processor(&mut Attenuator::new(0.5), &input);
+///
+/// output.assert_gain(&input, -6.02, 0.1);
+/// output.assert_finite();
+/// ```
+pub struct DspHarness {
+    sample_rate: u32,
+    in_channels: usize,
+    out_channels: usize,
+    block_size: usize,
+}
+
+impl DspHarness {
+    /// `block_size` is the number of frames given to each call, FMOD uses 1024 by default.
+    pub fn new(sample_rate: u32, in_channels: usize, out_channels: usize,
+               block_size: usize) -> DspHarness {
+        assert!(sample_rate > 0 && in_channels > 0 && out_channels > 0 && block_size > 0,
+                "the harness needs a sample rate, channels and a block size");
+        DspHarness {
+            sample_rate: sample_rate,
+            in_channels: in_channels,
+            out_channels: out_channels,
+            block_size: block_size,
+        }
+    }
+
+    /// Generates `frames` frames of the signal with the input channel count of the harness.
+    pub fn generate(&self, signal: TestSignal, frames: usize) -> TestBuffer {
+        let mut samples = Vec::with_capacity(frames * self.in_channels);
+        let mut state = match signal {
+            TestSignal::Noise { seed, .. } => if seed == 0 { 1 } else { seed },
+            _ => 1
+        };
+
+        for frame in 0..frames {
+            let value = match signal {
+                TestSignal::Impulse => if frame == 0 { 1f32 } else { 0f32 },
+                TestSignal::Sine { frequency, amplitude } => {
+                    amplitude * (2f32 * PI * frequency * frame as f32
+                                 / self.sample_rate as f32).sin()
+                }
+                TestSignal::Noise { amplitude, .. } => {
+                    // xorshift32
+                    state ^= state << 13;
+                    state ^= state >> 17;
+                    state ^= state << 5;
+                    amplitude * (state as f32 / u32::MAX as f32 * 2f32 - 1f32)
+                }
+                TestSignal::Silence => 0f32
+            };
+
+            for _ in 0..self.in_channels {
+                samples.push(value);
+            }
+        }
+        TestBuffer {
+            samples: samples,
+            channels: self.in_channels,
+            sample_rate: self.sample_rate,
+        }
+    }
+
+    fn check_input(&self, input: &TestBuffer) {
+        assert!(input.channels == self.in_channels, "input has {} channels, the harness expects {}",
+                input.channels, self.in_channels);
+    }
+
+    fn new_output(&self, input: &TestBuffer) -> TestBuffer {
+        TestBuffer {
+            samples: vec![0f32; input.get_frames() * self.out_channels],
+            channels: self.out_channels,
+            sample_rate: self.sample_rate,
+        }
+    }
+
+    /// Runs the processor over the whole input and returns its output.
+    pub fn run_processor(&self, processor: &mut dyn DspProcessor,
+                         input: &TestBuffer) -> TestBuffer {
+        self.check_input(input);
+        let mut output = self.new_output(input);
+        let frames = input.get_frames();
+        let mut start = 0;
+
+        while start < frames {
+            let length = ::std::cmp::min(self.block_size, frames - start);
+
+            processor.process(&input.samples[start * self.in_channels..
+                                             (start + length) * self.in_channels],
+                              &mut output.samples[start * self.out_channels..
+                                                  (start + length) * self.out_channels],
+                              length, self.in_channels, self.out_channels);
+            start += length;
+        }
+        output
+    }
+
+    /// Runs a read callback over the whole input and returns its output. The
+    /// [`DspState`](struct.DspState.html) given to the callback has a null instance, so the
+    /// callback must not call methods of `dsp_state.instance`.
+    pub fn run_callback(&self, callback: DspReadCallback,
+                        input: &TestBuffer) -> Result<TestBuffer, ::Status> {
+        let callback = match callback {
+            Some(c) => c,
+            None => return Err(::Status::InvalidParam)
+        };
+        self.check_input(input);
+        // the instance is null, dropping it would only reach for FMOD
+        let state = ::std::mem::ManuallyDrop::new(dsp::from_state_ptr(ffi::FMOD_DSP_STATE {
+            instance: ::std::ptr::null_mut(),
+            plugin_data: ::std::ptr::null_mut(),
+            speaker_mask: 0,
+        }));
+        let mut in_block = vec![0f32; self.block_size * self.in_channels];
+        let mut output = self.new_output(input);
+        let frames = input.get_frames();
+        let mut start = 0;
+
+        while start < frames {
+            let length = ::std::cmp::min(self.block_size, frames - start);
+            let in_len = length * self.in_channels;
+
+            // FMOD gives a mutable input buffer, so the callback works on a copy
+            in_block[..in_len].copy_from_slice(&input.samples[start * self.in_channels..
+                                                              start * self.in_channels + in_len]);
+            match callback(&state, &mut in_block[..in_len],
+                           &mut output.samples[start * self.out_channels..
+                                               (start + length) * self.out_channels],
+                           length as u32, self.in_channels as i32, self.out_channels as i32) {
+                ::Status::Ok => {}
+                e => return Err(e)
+            }
+            start += length;
+        }
+        Ok(output)
+    }
+}
+
+/// Computes the FFT of `re`/`im` in place. Their length must be a power of two.
+fn fft(re: &mut [f32], im: &mut [f32]) {
+    let n = re.len();
+    let mut j = 0;
+
+    for i in 1..n {
+        let mut bit = n >> 1;
+
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut length = 2;
+
+    while length <= n {
+        let angle = -2f32 * PI / length as f32;
+
+        for start in (0..n).step_by(length) {
+            for k in 0..length / 2 {
+                let (w_re, w_im) = ((angle * k as f32).cos(), (angle * k as f32).sin());
+                let (a, b) = (start + k, start + k + length / 2);
+                let t_re = re[b] * w_re - im[b] * w_im;
+                let t_im = re[b] * w_im + im[b] * w_re;
+
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        length <<= 1;
+    }
+}
+
+fn to_db(ratio: f32) -> f32 {
+    20f32 * ratio.log10()
+}
+
+impl TestBuffer {
+    pub fn get_frames(&self) -> usize {
+        self.samples.len() / self.channels
+    }
+
+    /// Returns the samples of one channel.
+    pub fn get_channel(&self, channel: usize) -> Vec<f32> {
+        assert!(channel < self.channels, "channel {} out of bounds", channel);
+        self.samples.iter().skip(channel).step_by(self.channels).cloned().collect()
+    }
+
+    pub fn get_peak(&self, channel: usize) -> f32 {
+        self.get_channel(channel).iter().fold(0f32, |peak, s| peak.max(s.abs()))
+    }
+
+    pub fn get_rms(&self, channel: usize) -> f32 {
+        let samples = self.get_channel(channel);
+
+        if samples.is_empty() {
+            return 0f32;
+        }
+        (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
+    }
+
+    /// Returns the gain in dB of this buffer relative to `input`, measured on the RMS of the
+    /// given channels.
+    pub fn get_gain(&self, input: &TestBuffer, channel: usize, input_channel: usize) -> f32 {
+        to_db(self.get_rms(channel) / input.get_rms(input_channel))
+    }
+
+    /// Returns the magnitude spectrum of a channel, from 0 Hz to the Nyquist frequency. A Hann
+    /// window is applied and the channel is zero-padded to a power of two; magnitudes are scaled
+    /// so that a sine of amplitude 1 gives a peak close to 1.
+    pub fn get_spectrum(&self, channel: usize) -> Vec<f32> {
+        self.compute_spectrum(channel, true)
+    }
+
+    fn compute_spectrum(&self, channel: usize, windowed: bool) -> Vec<f32> {
+        let samples = self.get_channel(channel);
+        let size = ::std::cmp::max(samples.len().next_power_of_two(), 2);
+        let mut re = vec![0f32; size];
+        let mut im = vec![0f32; size];
+        let mut window_sum = 0f32;
+
+        for (pos, sample) in samples.iter().enumerate() {
+            let window = if windowed && samples.len() > 1 {
+                0.5f32 - 0.5f32 * (2f32 * PI * pos as f32 / (samples.len() - 1) as f32).cos()
+            } else {
+                1f32
+            };
+
+            re[pos] = sample * window;
+            window_sum += window;
+        }
+        fft(&mut re, &mut im);
+        let scale = if window_sum > 0f32 { 2f32 / window_sum } else { 0f32 };
+
+        (0..size / 2 + 1).map(|bin| (re[bin] * re[bin] + im[bin] * im[bin]).sqrt() * scale)
+                         .collect()
+    }
+
+    /// Returns the spectrum magnitude at the bin closest to the given frequency.
+    pub fn get_magnitude_at(&self, channel: usize, frequency: f32) -> f32 {
+        let spectrum = self.get_spectrum(channel);
+        let bin_width = self.sample_rate as f32 / ((spectrum.len() - 1) * 2) as f32;
+        let bin = ((frequency / bin_width).round() as usize).min(spectrum.len() - 1);
+
+        spectrum[bin]
+    }
+
+    /// Returns the frequency response in dB of this buffer relative to `input` at each given
+    /// frequency. Best measured with an impulse input. No window is applied, since a Hann window
+    /// would erase the impulse.
+    pub fn get_frequency_response(&self, input: &TestBuffer, channel: usize,
+                                  frequencies: &[f32]) -> Vec<f32> {
+        let output = self.compute_spectrum(channel, false);
+        let input_spectrum = input.compute_spectrum(::std::cmp::min(channel, input.channels - 1),
+                                                    false);
+        let bin_width = self.sample_rate as f32 / ((output.len() - 1) * 2) as f32;
+
+        frequencies.iter().map(|frequency| {
+            let bin = ((frequency / bin_width).round() as usize).min(output.len() - 1);
+
+            match input_spectrum.get(bin) {
+                Some(i) => to_db(output[bin] / i),
+                None => f32::NEG_INFINITY
+            }
+        }).collect()
+    }
+
+    /// Returns the delay in frames of this buffer relative to `input`, found at the peak of their
+    /// cross-correlation, or `None` if the output is silent.
+    pub fn get_latency(&self, input: &TestBuffer, channel: usize,
+                       max_latency: usize) -> Option<usize> {
+        let output = self.get_channel(channel);
+        let input = input.get_channel(::std::cmp::min(channel, input.channels - 1));
+        let mut best = None;
+        let mut best_value = 0f32;
+
+        for lag in 0..::std::cmp::min(max_latency + 1, output.len()) {
+            let value = input.iter().zip(output[lag..].iter()).map(|(i, o)| i * o).sum::<f32>()
+                             .abs();
+
+            if value > best_value {
+                best_value = value;
+                best = Some(lag);
+            }
+        }
+        best
+    }
+
+    /// Returns the index of the first NaN or infinite sample.
+    pub fn find_non_finite(&self) -> Option<usize> {
+        self.samples.iter().position(|s| !s.is_finite())
+    }
+
+    /// Returns the index of the first denormal sample.
+    pub fn find_denormal(&self) -> Option<usize> {
+        self.samples.iter().position(|s| s.is_subnormal())
+    }
+
+    /// Panics if the gain relative to `input`, averaged over the channels, is not within
+    /// `tolerance` dB of `expected`.
+    pub fn assert_gain(&self, input: &TestBuffer, expected: f32, tolerance: f32) {
+        let gain = (0..self.channels).map(|c| {
+            self.get_gain(input, c, ::std::cmp::min(c, input.channels - 1))
+        }).sum::<f32>() / self.channels as f32;
+
+        assert!((gain - expected).abs() <= tolerance, "gain is {} dB, expected {} dB ± {}",
+                gain, expected, tolerance);
+    }
+
+    /// Panics if the response at any of the `(frequency, dB)` points is not within `tolerance`
+    /// dB.
+    pub fn assert_frequency_response(&self, input: &TestBuffer, points: &[(f32, f32)],
+                                     tolerance: f32) {
+        let frequencies = points.iter().map(|p| p.0).collect::<Vec<f32>>();
+
+        for channel in 0..self.channels {
+            let response = self.get_frequency_response(input, channel, &frequencies);
+
+            for (&(frequency, expected), measured) in points.iter().zip(response.iter()) {
+                assert!((measured - expected).abs() <= tolerance,
+                        "channel {}: response at {} Hz is {} dB, expected {} dB ± {}",
+                        channel, frequency, measured, expected, tolerance);
+            }
+        }
+    }
+
+    /// Panics if the latency of the first channel relative to `input` isn't `expected` frames.
+    pub fn assert_latency(&self, input: &TestBuffer, expected: usize) {
+        let latency = self.get_latency(input, 0, expected * 2 + self.get_frames() / 2);
+
+        assert!(latency == Some(expected), "latency is {:?} frames, expected {}", latency,
+                expected);
+    }
+
+    /// Panics if the buffer contains NaN, infinite or denormal samples.
+    pub fn assert_finite(&self) {
+        if let Some(pos) = self.find_non_finite() {
+            panic!("sample {} (frame {}) is {}", pos, pos / self.channels, self.samples[pos]);
+        }
+        if let Some(pos) = self.find_denormal() {
+            panic!("sample {} (frame {}) is denormal", pos, pos / self.channels);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Gain(f32);
+
+    impl DspProcessor for Gain {
+        fn process(&mut self, input: &[f32], output: &mut [f32], frames: usize,
+                   in_channels: usize, out_channels: usize) {
+            for frame in 0..frames {
+                for channel in 0..out_channels {
+                    output[frame * out_channels + channel] =
+                        input[frame * in_channels + channel % in_channels] * self.0;
+                }
+            }
+        }
+    }
+
+    /// Outputs `x[n] * mix + x[n - 1] * (1 - mix)`, so a mix of 0 is a one-sample delay and a
+    /// mix of 0.5 a two-tap average. The history is kept across blocks.
+    struct Delay {
+        mix: f32,
+        history: Vec<f32>,
+    }
+
+    impl Delay {
+        fn new(mix: f32) -> Delay {
+            Delay { mix: mix, history: Vec::new() }
+        }
+    }
+
+    impl DspProcessor for Delay {
+        fn process(&mut self, input: &[f32], output: &mut [f32], frames: usize,
+                   in_channels: usize, _out_channels: usize) {
+            self.history.resize(in_channels, 0f32);
+            for frame in 0..frames {
+                for channel in 0..in_channels {
+                    let pos = frame * in_channels + channel;
+                    let previous = self.history[channel];
+
+                    output[pos] = input[pos] * self.mix + previous * (1f32 - self.mix);
+                    self.history[channel] = input[pos];
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn generate_signals() {
+        let harness = DspHarness::new(48000, 2, 2, 64);
+        let impulse = harness.generate(TestSignal::Impulse, 100);
+        let sine = harness.generate(TestSignal::Sine { frequency: 1000f32, amplitude: 0.5 }, 4800);
+        let noise = harness.generate(TestSignal::Noise { amplitude: 0.25, seed: 7 }, 1000);
+
+        assert_eq!(impulse.get_frames(), 100);
+        assert_eq!(impulse.samples.len(), 200);
+        assert_eq!(impulse.get_channel(1)[0], 1f32);
+        assert_eq!(impulse.get_rms(0), 0.1f32);
+        assert!((sine.get_peak(0) - 0.5).abs() < 1e-3);
+        assert!((sine.get_rms(1) - 0.5 / 2f32.sqrt()).abs() < 1e-3);
+        assert_eq!(sine.get_channel(0), sine.get_channel(1));
+        assert!(noise.get_peak(0) <= 0.25);
+        assert_eq!(noise, harness.generate(TestSignal::Noise { amplitude: 0.25, seed: 7 }, 1000));
+        assert!(noise != harness.generate(TestSignal::Noise { amplitude: 0.25, seed: 8 }, 1000));
+        assert_eq!(harness.generate(TestSignal::Silence, 10).get_peak(0), 0f32);
+    }
+
+    #[test]
+    fn gain_stage() {
+        let harness = DspHarness::new(44100, 2, 2, 256);
+        let input = harness.generate(TestSignal::Sine { frequency: 1000f32, amplitude: 0.5 }, 4000);
+        let output = harness.run_processor(&mut Gain(0.5), &input);
+
+        assert_eq!(output.get_frames(), input.get_frames());
+        assert!((output.get_gain(&input, 1, 1) + 6.0206).abs() < 1e-3);
+        output.assert_gain(&input, -6.02, 0.01);
+        output.assert_latency(&input, 0);
+        output.assert_finite();
+    }
+
+    #[test]
+    #[should_panic(expected = "gain is")]
+    fn wrong_gain() {
+        let harness = DspHarness::new(44100, 1, 1, 256);
+        let input = harness.generate(TestSignal::Noise { amplitude: 1f32, seed: 1 }, 1000);
+
+        harness.run_processor(&mut Gain(0.5), &input).assert_gain(&input, 0f32, 1f32);
+    }
+
+    #[test]
+    fn mono_to_stereo() {
+        let harness = DspHarness::new(44100, 1, 2, 100);
+        let input = harness.generate(TestSignal::Noise { amplitude: 1f32, seed: 3 }, 1000);
+        let output = harness.run_processor(&mut Gain(2f32), &input);
+
+        assert_eq!(output.channels, 2);
+        assert_eq!(output.get_channel(0), output.get_channel(1));
+        output.assert_gain(&input, 6.02, 0.01);
+    }
+
+    #[test]
+    fn one_sample_delay() {
+        // 1000 frames don't fill the last 64-frame block, and the history crosses every block
+        let harness = DspHarness::new(44100, 2, 2, 64);
+        let impulse = harness.generate(TestSignal::Impulse, 1000);
+        let output = harness.run_processor(&mut Delay::new(0f32), &impulse);
+
+        assert_eq!(&output.get_channel(0)[..3], &[0f32, 1f32, 0f32]);
+        output.assert_latency(&impulse, 1);
+
+        let noise = harness.generate(TestSignal::Noise { amplitude: 1f32, seed: 42 }, 1000);
+        let output = harness.run_processor(&mut Delay::new(0f32), &noise);
+
+        output.assert_latency(&noise, 1);
+        assert_eq!(output.get_channel(1)[1..], noise.get_channel(1)[..999]);
+    }
+
+    #[test]
+    #[should_panic(expected = "latency is")]
+    fn wrong_latency() {
+        let harness = DspHarness::new(44100, 1, 1, 64);
+        let input = harness.generate(TestSignal::Noise { amplitude: 1f32, seed: 5 }, 1000);
+
+        harness.run_processor(&mut Delay::new(0f32), &input).assert_latency(&input, 0);
+    }
+
+    #[test]
+    fn spectrum_of_sines() {
+        let harness = DspHarness::new(44100, 1, 1, 1024);
+        // 43.066 Hz per bin with 1024 frames: 4306.6 Hz is bin 100 and 861.3 Hz is bin 20
+        let bin_width = 44100f32 / 1024f32;
+        let mut input = harness.generate(TestSignal::Sine { frequency: bin_width * 100f32,
+                                                            amplitude: 0.8 }, 1024);
+        let low = harness.generate(TestSignal::Sine { frequency: bin_width * 20f32,
+                                                      amplitude: 0.2 }, 1024);
+
+        for (sample, other) in input.samples.iter_mut().zip(low.samples.iter()) {
+            *sample += other;
+        }
+        let spectrum = input.get_spectrum(0);
+
+        assert_eq!(spectrum.len(), 513);
+        assert!((spectrum[100] - 0.8).abs() < 0.01, "{}", spectrum[100]);
+        assert!((spectrum[20] - 0.2).abs() < 0.01, "{}", spectrum[20]);
+        assert!((input.get_magnitude_at(0, bin_width * 100f32) - 0.8).abs() < 0.01);
+        for (bin, magnitude) in spectrum.iter().enumerate() {
+            if (bin as i32 - 100).abs() > 1 && (bin as i32 - 20).abs() > 1 {
+                assert!(*magnitude < 1e-3, "bin {} is {}", bin, magnitude);
+            }
+        }
+
+        // not on a bin, the Hann window keeps the peak within 1.5 dB
+        let input = harness.generate(TestSignal::Sine { frequency: 1000f32, amplitude: 1f32 },
+                                     4096);
+        let peak = input.get_magnitude_at(0, 1000f32);
+
+        assert!(to_db(peak) > -1.5 && peak <= 1.01, "{}", peak);
+    }
+
+    #[test]
+    fn frequency_response() {
+        let harness = DspHarness::new(44100, 1, 1, 128);
+        let impulse = harness.generate(TestSignal::Impulse, 2048);
+
+        harness.run_processor(&mut Gain(0.5), &impulse)
+               .assert_frequency_response(&impulse, &[(100f32, -6.02), (10000f32, -6.02)], 0.01);
+        harness.run_processor(&mut Delay::new(0f32), &impulse)
+               .assert_frequency_response(&impulse, &[(100f32, 0f32), (20000f32, 0f32)], 0.01);
+
+        // the two-tap average is cos(pi * f / rate): -3.01 dB at a quarter of the rate
+        let output = harness.run_processor(&mut Delay::new(0.5), &impulse);
+
+        output.assert_frequency_response(&impulse, &[(0f32, 0f32), (11025f32, -3.01)], 0.01);
+        assert!(output.get_frequency_response(&impulse, 0, &[22050f32])[0] < -60f32);
+    }
+
+    fn halve(_: &dsp::DspState, in_buffer: &mut [f32], out_buffer: &mut [f32], length: u32,
+             in_channels: i32, out_channels: i32) -> ::Status {
+        if in_buffer.len() != length as usize * in_channels as usize
+           || out_buffer.len() != length as usize * out_channels as usize {
+            return ::Status::InvalidParam;
+        }
+        for (o, i) in out_buffer.iter_mut().zip(in_buffer.iter_mut()) {
+            *o = *i * 0.5f32;
+            // the harness gives a copy of the input, writing to it must not change the source
+            *i = 0f32;
+        }
+        ::Status::Ok
+    }
+
+    fn fail(_: &dsp::DspState, _: &mut [f32], _: &mut [f32], _: u32, _: i32, _: i32) -> ::Status {
+        ::Status::Internal
+    }
+
+    #[test]
+    fn read_callback() {
+        let harness = DspHarness::new(44100, 2, 2, 100);
+        let input = harness.generate(TestSignal::Noise { amplitude: 1f32, seed: 9 }, 1050);
+        let copy = input.clone();
+        let output = harness.run_callback(Some(halve), &input).unwrap();
+
+        assert_eq!(input, copy);
+        output.assert_gain(&input, -6.02, 0.01);
+        output.assert_latency(&input, 0);
+        assert_eq!(harness.run_callback(Some(fail), &input), Err(::Status::Internal));
+        assert_eq!(harness.run_callback(None, &input), Err(::Status::InvalidParam));
+    }
+
+    #[test]
+    fn bad_samples() {
+        let mut buffer = TestBuffer { samples: vec![0.5f32; 8], channels: 2, sample_rate: 44100 };
+
+        assert_eq!(buffer.find_non_finite(), None);
+        assert_eq!(buffer.find_denormal(), None);
+        buffer.assert_finite();
+        buffer.samples[5] = 1e-40f32;
+        assert_eq!(buffer.find_denormal(), Some(5));
+        assert_eq!(buffer.find_non_finite(), None);
+        buffer.samples[3] = f32::NAN;
+        assert_eq!(buffer.find_non_finite(), Some(3));
+    }
+
+    #[test]
+    #[should_panic(expected = "is denormal")]
+    fn denormal_is_not_finite() {
+        TestBuffer { samples: vec![0f32, f32::MIN_POSITIVE / 4f32], channels: 1,
+                     sample_rate: 44100 }.assert_finite();
+    }
+}
@@ -0,0 +1,206 @@
+/*
+* Rust-FMOD - Copyright (c) 2018 Gomez Guillaume.
+*
+* The Original software, FmodEx library, is provided by FIRELIGHT TECHNOLOGIES.
+*
+* This software is provided 'as-is', without any express or implied warranty.
+* In no event will the authors be held liable for any damages arising from
+* the use of this software.
+*
+* Permission is granted to anyone to use this software for any purpose,
+* including commercial applications, and to alter it and redistribute it
+* freely, subject to the following restrictions:
+*
+* 1. The origin of this software must not be misrepresented; you must not claim
+*    that you wrote the original software. If you use this software in a product,
+*    an acknowledgment in the product documentation would be appreciated but is
+*    not required.
+*
+* 2. Altered source versions must be plainly marked as such, and must not be
+*    misrepresented as being the original software.
+*
+* 3. This notice may not be removed or altered from any source distribution.
+*/
+
+use dsp::Dsp;
+use fmod_sys::Sys;
+use std::collections::BTreeMap;
+
+/// Snapshot of the parameters of a DSP, created with
+/// [`Dsp::to_preset`](struct.Dsp.html#method.to_preset) and applied with
+/// [`Dsp::apply_preset`](struct.Dsp.html#method.apply_preset).
+///
+/// Parameters are stored by name so presets stay readable. A parameter without a name, or with
+/// the same name as a previous one, is stored as `#index`.
+///
+/// With the `serde` feature, presets can be saved in any format supported by serde. In TOML:
+///
+/// ```toml
+/// dsp_type = "Echo"
+/// name = "FMOD Echo"
+///
+/// [parameters]
+/// Delay = 350.0
+/// Decayratio = 0.4
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DspPreset {
+    pub dsp_type  : ::DspType,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub name      : String,
+    pub parameters: BTreeMap<String, f32>,
+}
+
+/// A send of a [`DspChainPreset`](struct.DspChainPreset.html).
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DspSendPreset {
+    pub effect: DspPreset,
+    pub mix   : f32,
+}
+
+/// Presets of a whole effect stack, such as a [`DspChain`](struct.DspChain.html) or the effects
+/// of a [`Mixer`](struct.Mixer.html) bus.
+#[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct DspChainPreset {
+    /// Effects in order, the first one being the closest to the head.
+    pub effects: Vec<DspPreset>,
+    pub sends  : Vec<DspSendPreset>,
+}
+
+/// Returns the key under which each parameter of the DSP is stored.
+fn get_keys(dsp: &Dsp) -> Result<Vec<String>, ::Status> {
+    let num_parameters = dsp.get_num_parameters()?;
+    let mut keys : Vec<String> = Vec::with_capacity(num_parameters as usize);
+
+    for index in 0..num_parameters {
+        let name = match dsp.get_parameter_info(index) {
+            Ok(info) => info.name,
+            Err(e) => return Err(e)
+        };
+
+        if name.is_empty() || keys.contains(&name) {
+            keys.push(format!("#{}", index));
+        } else {
+            keys.push(name);
+        }
+    }
+    Ok(keys)
+}
+
+pub fn to_preset(dsp: &Dsp) -> Result<DspPreset, ::Status> {
+    let dsp_type = dsp.get_type()?;
+    let keys = get_keys(dsp)?;
+    let mut parameters = BTreeMap::new();
+
+    for (index, key) in keys.into_iter().enumerate() {
+        match dsp.get_parameter(index as i32, 16) {
+            Ok((value, _)) => {
+                parameters.insert(key, value);
+            }
+            Err(::RStatus::FMOD(e)) => return Err(e),
+            Err(::RStatus::Other(_)) => return Err(::Status::InvalidParam)
+        }
+    }
+    Ok(DspPreset {
+        dsp_type: dsp_type,
+        name: dsp.get_info().map(|i| i.name).unwrap_or_default(),
+        parameters: parameters,
+    })
+}
+
+/// Checks that the preset can be applied to the DSP and returns the parameter keys of the DSP.
+pub fn check_preset(dsp: &Dsp, preset: &DspPreset) -> Result<Vec<String>, ::Status> {
+    match dsp.get_type() {
+        Ok(t) if t == preset.dsp_type => {}
+        Ok(_) => return Err(::Status::InvalidParam),
+        Err(e) => return Err(e)
+    }
+    let keys = get_keys(dsp)?;
+
+    if preset.parameters.keys().any(|name| !keys.contains(name)) {
+        return Err(::Status::InvalidParam);
+    }
+    Ok(keys)
+}
+
+pub fn apply_preset(dsp: &Dsp, preset: &DspPreset) -> ::Status {
+    // checked first so a bad preset doesn't leave the DSP half changed
+    match check_preset(dsp, preset) {
+        Ok(keys) => set_parameters(dsp, preset, &keys),
+        Err(e) => e
+    }
+}
+
+fn set_parameters(dsp: &Dsp, preset: &DspPreset, keys: &[String]) -> ::Status {
+    for (index, key) in keys.iter().enumerate() {
+        if let Some(value) = preset.parameters.get(key) {
+            match dsp.set_parameter(index as i32, *value) {
+                ::Status::Ok => {}
+                e => return e
+            }
+        }
+    }
+    ::Status::Ok
+}
+
+impl DspPreset {
+    /// Creates a built-in DSP of the preset type and applies the preset to it. Returns
+    /// `Err(Status::Unsupported)` for presets of custom DSPs.
+    pub fn create_DSP(&self, sys: &Sys) -> Result<Dsp, ::Status> {
+        if self.dsp_type == ::DspType::Unknown {
+            return Err(::Status::Unsupported);
+        }
+        let dsp = sys.create_DSP_by_type(self.dsp_type)?;
+
+        match apply_preset(&dsp, self) {
+            ::Status::Ok => Ok(dsp),
+            e => Err(e)
+        }
+    }
+}
+
+impl DspChainPreset {
+    /// Saves the given effects, such as the ones returned by
+    /// [`Mixer::get_effects`](struct.Mixer.html#method.get_effects).
+    pub fn from_effects(effects: &[Dsp]) -> Result<DspChainPreset, ::Status> {
+        let mut presets = Vec::with_capacity(effects.len());
+
+        for effect in effects.iter() {
+            match to_preset(effect) {
+                Ok(p) => presets.push(p),
+                Err(e) => return Err(e)
+            }
+        }
+        Ok(DspChainPreset {
+            effects: presets,
+            sends: Vec::new(),
+        })
+    }
+
+    /// Applies the effect presets to the given effects, in order. Both must have the same length
+    /// and types. Every pair is checked before any effect is changed.
+    pub fn apply_to_effects(&self, effects: &[Dsp]) -> ::Status {
+        if effects.len() != self.effects.len() {
+            return ::Status::InvalidParam;
+        }
+        let mut keys = Vec::with_capacity(effects.len());
+
+        for (effect, preset) in effects.iter().zip(self.effects.iter()) {
+            match check_preset(effect, preset) {
+                Ok(k) => keys.push(k),
+                Err(e) => return e
+            }
+        }
+        for ((effect, preset), keys) in effects.iter().zip(self.effects.iter()).zip(keys.iter()) {
+            match set_parameters(effect, preset, keys) {
+                ::Status::Ok => {}
+                e => return e
+            }
+        }
+        ::Status::Ok
+    }
+}
@@ -0,0 +1,260 @@
+/*
+* Rust-FMOD - Copyright (c) 2018 Gomez Guillaume.
+*
+* The Original software, FmodEx library, is provided by FIRELIGHT TECHNOLOGIES.
+*
+* This software is provided 'as-is', without any express or implied warranty.
+* In no event will the authors be held liable for any damages arising from
+* the use of this software.
+*
+* Permission is granted to anyone to use this software for any purpose,
+* including commercial applications, and to alter it and redistribute it
+* freely, subject to the following restrictions:
+*
+* 1. The origin of this software must not be misrepresented; you must not claim
+*    that you wrote the original software. If you use this software in a product,
+*    an acknowledgment in the product documentation would be appreciated but is
+*    not required.
+*
+* 2. Altered source versions must be plainly marked as such, and must not be
+*    misrepresented as being the original software.
+*
+* 3. This notice may not be removed or altered from any source distribution.
+*/
+
+use ffi;
+use channel_group::ChannelGroup;
+use std::time::{Duration, Instant};
+
+/// Number of samples read by [`DuckDetection::Level`](enum.DuckDetection.html).
+const WAVE_SIZE: usize = 512;
+
+#[derive(Clone, PartialEq, Debug, Copy)]
+/// How a [`Ducker`](struct.Ducker.html) decides that its source is active.
+pub enum DuckDetection {
+    /// The source is active as long as it is playing at least one channel.
+    Activity,
+    /// The source is active when the RMS of its output (read with
+    /// [`ChannelGroup::get_wave_data`](struct.ChannelGroup.html#method.get_wave_data)) is above
+    /// the given linear threshold.
+    Level(f32),
+}
+
+struct Target {
+    group: ChannelGroup,
+    volume: f32,
+}
+
+/// Lowers the volume of target channel groups while a source channel group is active.
+///
+/// [`Ducker::update`](struct.Ducker.html#method.update) has to be called alongside
+/// [`Sys::update`](struct.Sys.html#method.update). The original volume of a target is restored
+/// when it is removed or when the ducker is dropped.
+///
+/// ```ignore
+/// let mut ducker = rfmod::Ducker::new(&dialogue, rfmod::DuckDetection::Activity);
+///
+/// ducker.set_amount(0.7);
+/// ducker.add_target(&music);
+/// loop {
+///     fmod.update();
+///     ducker.update();
+/// }
+/// ```
+pub struct Ducker {
+    source: ChannelGroup,
+    detection: DuckDetection,
+    targets: Vec<Target>,
+    amount: f32,
+    attack: Duration,
+    hold: Duration,
+    release: Duration,
+    envelope: f32,
+    hold_left: Duration,
+    last_update: Option<Instant>,
+}
+
+impl Ducker {
+    /// Creates a ducker lowering its targets by half, with an attack of 50ms, a hold of 200ms and
+    /// a release of 500ms.
+    pub fn new(source: &ChannelGroup, detection: DuckDetection) -> Ducker {
+        Ducker {
+            source: ffi::FFI::wrap(ffi::FFI::unwrap(source)),
+            detection: detection,
+            targets: Vec::new(),
+            amount: 0.5f32,
+            attack: Duration::from_millis(50),
+            hold: Duration::from_millis(200),
+            release: Duration::from_millis(500),
+            envelope: 0f32,
+            hold_left: Duration::from_millis(0),
+            last_update: None,
+        }
+    }
+
+    /// Starts ducking the group. Its current volume is kept as its original volume.
+    pub fn add_target(&mut self, group: &ChannelGroup) -> ::Status {
+        let ptr = ffi::FFI::unwrap(group);
+
+        if self.targets.iter().any(|t| ffi::FFI::unwrap(&t.group) == ptr) {
+            return ::Status::InvalidParam;
+        }
+        match group.get_volume() {
+            Ok(volume) => {
+                self.targets.push(Target {
+                    group: ffi::FFI::wrap(ptr),
+                    volume: volume,
+                });
+                self.apply()
+            }
+            Err(e) => e
+        }
+    }
+
+    /// Stops ducking the group and restores its original volume.
+    pub fn remove_target(&mut self, group: &ChannelGroup) -> ::Status {
+        let ptr = ffi::FFI::unwrap(group);
+
+        match self.targets.iter().position(|t| ffi::FFI::unwrap(&t.group) == ptr) {
+            Some(pos) => {
+                let target = self.targets.remove(pos);
+
+                target.group.set_volume(target.volume)
+            }
+            None => ::Status::InvalidParam
+        }
+    }
+
+    /// Changes the volume a target goes back to once the source stops.
+    pub fn set_target_volume(&mut self, group: &ChannelGroup, volume: f32) -> ::Status {
+        let ptr = ffi::FFI::unwrap(group);
+
+        match self.targets.iter().position(|t| ffi::FFI::unwrap(&t.group) == ptr) {
+            Some(pos) => {
+                self.targets[pos].volume = volume;
+                self.apply()
+            }
+            None => ::Status::InvalidParam
+        }
+    }
+
+    pub fn get_num_targets(&self) -> usize {
+        self.targets.len()
+    }
+
+    /// Sets how much the targets are lowered, from 0 (not at all) to 1 (silenced).
+    pub fn set_amount(&mut self, amount: f32) -> ::Status {
+        if !(0f32..=1f32).contains(&amount) {
+            return ::Status::InvalidParam;
+        }
+        self.amount = amount;
+        self.apply()
+    }
+
+    pub fn get_amount(&self) -> f32 {
+        self.amount
+    }
+
+    /// Sets the time taken to reach full ducking, how long it is kept once the source stops, and
+    /// the time taken to go back to the original volume.
+    pub fn set_times(&mut self, attack: Duration, hold: Duration, release: Duration) {
+        self.attack = attack;
+        self.hold = hold;
+        self.release = release;
+    }
+
+    /// Returns (attack, hold, release).
+    pub fn get_times(&self) -> (Duration, Duration, Duration) {
+        (self.attack, self.hold, self.release)
+    }
+
+    pub fn set_detection(&mut self, detection: DuckDetection) {
+        self.detection = detection;
+    }
+
+    pub fn get_detection(&self) -> DuckDetection {
+        self.detection
+    }
+
+    /// Returns how much the targets are currently ducked, from 0 to 1.
+    pub fn get_envelope(&self) -> f32 {
+        self.envelope
+    }
+
+    pub fn is_source_active(&self) -> Result<bool, ::Status> {
+        match self.detection {
+            DuckDetection::Activity => self.source.get_num_channels().map(|n| n > 0),
+            DuckDetection::Level(threshold) => {
+                match self.source.get_wave_data(WAVE_SIZE, 0) {
+                    Ok(data) => {
+                        let sum = data.iter().fold(0f32, |sum, s| sum + s * s);
+
+                        Ok((sum / WAVE_SIZE as f32).sqrt() > threshold)
+                    }
+                    Err(e) => Err(e)
+                }
+            }
+        }
+    }
+
+    /// Updates the ducking with the time elapsed since the previous call.
+    pub fn update(&mut self) -> ::Status {
+        let now = Instant::now();
+        let elapsed = match self.last_update {
+            Some(last) => now.duration_since(last),
+            None => Duration::from_millis(0),
+        };
+
+        self.last_update = Some(now);
+        self.update_by(elapsed)
+    }
+
+    /// Updates the ducking as if `elapsed` had passed since the previous update.
+    pub fn update_by(&mut self, elapsed: Duration) -> ::Status {
+        let active = match self.is_source_active() {
+            Ok(a) => a,
+            Err(e) => return e
+        };
+
+        if active {
+            self.hold_left = self.hold;
+            self.envelope = step(self.envelope, elapsed, self.attack, 1f32);
+        } else if self.hold_left > elapsed {
+            self.hold_left -= elapsed;
+        } else {
+            let remaining = elapsed - self.hold_left;
+
+            self.hold_left = Duration::from_millis(0);
+            self.envelope = step(self.envelope, remaining, self.release, -1f32);
+        }
+        self.apply()
+    }
+
+    fn apply(&self) -> ::Status {
+        let gain = 1f32 - self.amount * self.envelope;
+
+        for target in self.targets.iter() {
+            match target.group.set_volume(target.volume * gain) {
+                ::Status::Ok => {}
+                e => return e
+            }
+        }
+        ::Status::Ok
+    }
+}
+
+impl Drop for Ducker {
+    fn drop(&mut self) {
+        for target in self.targets.iter() {
+            target.group.set_volume(target.volume);
+        }
+    }
+}
+
+fn step(envelope: f32, elapsed: Duration, time: Duration, direction: f32) -> f32 {
+    let time = time.as_secs() as f32 + time.subsec_nanos() as f32 / 1_000_000_000f32;
+    let elapsed = elapsed.as_secs() as f32 + elapsed.subsec_nanos() as f32 / 1_000_000_000f32;
+    let delta = if time > 0f32 { elapsed / time } else { 1f32 };
+
+    (envelope + direction * delta).clamp(0f32, 1f32)
+}
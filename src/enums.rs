@@ -785,6 +785,7 @@ pub enum SoundGroupBehavior {
 }
 
 #[derive(Clone, PartialEq, PartialOrd, Debug, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[repr(C)]
 /// These definitions can be used for creating FMOD defined special effects or DSP units.
 /// Used with [`Dsp::set_parameter`](../struct.Dsp.html#method.set_parameter) and
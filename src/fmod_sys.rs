@@ -36,8 +36,10 @@ use dsp::Dsp;
 use vector;
 use reverb_properties;
 use geometry;
+use geometry_obj;
 use reverb;
 use dsp_connection;
+use dsp_graph;
 use std::default::Default;
 use callbacks::*;
 use std;
@@ -45,6 +47,7 @@ use file;
 use libc::FILE;
 use c_vec::CVec;
 use std::ffi::CString;
+use std::io::Read;
 
 fn get_saved_sys_callback<'r>() -> &'r mut SysCallback {
     static mut CALLBACK : SysCallback = SysCallback {
@@ -1059,6 +1062,22 @@ pub fn from_memory_usage_details_ptr(details: ffi::FMOD_MEMORY_USAGE_DETAILS) ->
     }
 }
 
+fn find_channel_group(group: channel_group::ChannelGroup,
+                      name: &str) -> Option<channel_group::ChannelGroup> {
+    match group.get_name(channel::CHANNEL_GROUP_NAME_LEN) {
+        Ok(ref n) if n.trim_end_matches('\0') == name => return Some(group),
+        _ => {}
+    }
+    for index in 0..group.get_num_groups().unwrap_or(0) {
+        if let Ok(child) = group.get_group(index) {
+            if let Some(g) = find_channel_group(child, name) {
+                return Some(g);
+            }
+        }
+    }
+    None
+}
+
 /// FMOD System Object
 pub struct Sys {
     system: *mut ffi::FMOD_SYSTEM,
@@ -1258,7 +1277,7 @@ impl Sys {
         match unsafe { ffi::FMOD_System_CreateChannelGroup(self.system,
                                                           tmp_group_name.as_ptr() as *const c_char,
                                                           &mut channel_group) } {
-            ::Status::Ok => Ok(ffi::FFI::wrap(channel_group)),
+            ::Status::Ok => Ok(channel_group::from_ptr_first(channel_group)),
             e => Err(e)
         }
     }
@@ -1271,7 +1290,7 @@ impl Sys {
         match unsafe { ffi::FMOD_System_CreateSoundGroup(self.system,
                                                          tmp_group_name.as_ptr() as *const c_char,
                                                          &mut sound_group) } {
-            ::Status::Ok => Ok(ffi::FFI::wrap(sound_group)),
+            ::Status::Ok => Ok(sound_group::from_ptr_first(sound_group)),
             e => Err(e)
         }
     }
@@ -1295,14 +1314,46 @@ impl Sys {
         }
     }
 
-    pub fn create_DSP_with_description(&self, description: &mut dsp::DspDescription)
+    /// Creates a DSP from the description. The description can be dropped once the DSP is
+    /// created.
+    ///
+    /// Returns `Err(Status::InvalidParam)` if a parameter isn't valid, see
+    /// [`DspParameterDesc::validate`](struct.DspParameterDesc.html#method.validate).
+    pub fn create_DSP_with_description(&self, description: &dsp::DspDescription)
                                        -> Result<dsp::Dsp, ::Status> {
         let mut t_dsp = ::std::ptr::null_mut();
-        let mut t_description = dsp::get_description_ffi(description);
+        let (mut t_description, user_data) = match dsp::get_description_ffi(description) {
+            Ok(d) => d,
+            Err(::RStatus::FMOD(e)) => return Err(e),
+            Err(_) => return Err(::Status::InvalidParam)
+        };
 
         match unsafe { ffi::FMOD_System_CreateDSP(self.system, &mut t_description, &mut t_dsp) } {
             ::Status::Ok => Ok(dsp::from_ptr_first(t_dsp)),
-            e => Err(e)
+            e => {
+                dsp::free_user_data_ptr(user_data);
+                Err(e)
+            }
+        }
+    }
+
+    /// Creates a DSP calling the given processor. The processor is dropped when the DSP is
+    /// released.
+    pub fn create_dsp_from_processor(&self, processor: Box<dyn dsp::DspProcessor>)
+                                     -> Result<dsp::Dsp, ::Status> {
+        let mut t_dsp = ::std::ptr::null_mut();
+        let (mut t_description, user_data) = match dsp::get_processor_description_ffi(processor) {
+            Ok(d) => d,
+            Err(::RStatus::FMOD(e)) => return Err(e),
+            Err(_) => return Err(::Status::InvalidParam)
+        };
+
+        match unsafe { ffi::FMOD_System_CreateDSP(self.system, &mut t_description, &mut t_dsp) } {
+            ::Status::Ok => Ok(dsp::from_ptr_first(t_dsp)),
+            e => {
+                dsp::free_user_data_ptr(user_data);
+                Err(e)
+            }
         }
     }
 
@@ -1978,6 +2029,54 @@ impl Sys {
         }
     }
 
+    /// Returns a text report of the channel group tree, starting from the master channel group.
+    ///
+    /// Each group is listed with its volume, pitch, mute and paused state and the type of its
+    /// DSP head, followed by the channels playing in it with their current sound.
+    pub fn dump_mixer(&self) -> Result<String, ::Status> {
+        use std::fmt::Write;
+
+        let master = self.get_master_channel_group()?;
+        let mut out = String::new();
+
+        for (depth, group) in master.walk() {
+            let indent = "  ".repeat(depth);
+            let name = match group.get_full_name() {
+                Ok(n) => n,
+                Err(::RStatus::FMOD(e)) => return Err(e),
+                Err(_) => String::new()
+            };
+            let dsp_head = match group.get_DSP_head() {
+                Ok(d) => match d.get_type() {
+                    Ok(t) => format!("{:?}", t),
+                    Err(_) => "?".to_owned()
+                },
+                Err(_) => "none".to_owned()
+            };
+
+            let _ = writeln!(out, "{}[{}] volume={:.2} pitch={:.2} mute={} paused={} dsp_head={}",
+                             indent, name, group.get_volume().unwrap_or(0f32),
+                             group.get_pitch().unwrap_or(0f32), group.get_mute().unwrap_or(false),
+                             group.get_paused().unwrap_or(false), dsp_head);
+            for channel in group.channels() {
+                let sound = match channel.get_current_sound() {
+                    Ok(s) => s.get_name(channel::CHANNEL_GROUP_NAME_LEN)
+                              .map(|n| n.trim_end_matches('\0').to_owned())
+                              .unwrap_or_default(),
+                    Err(_) => String::new()
+                };
+
+                let _ = writeln!(out,
+                                 "{}  channel {} sound=\"{}\" position={}ms volume={:.2} paused={}",
+                                 indent, channel.get_index().unwrap_or(-1), sound,
+                                 channel.get_position(::TIMEUNIT_MS).unwrap_or(0),
+                                 channel.get_volume().unwrap_or(0f32),
+                                 channel.get_paused().unwrap_or(false));
+            }
+        }
+        Ok(out)
+    }
+
     pub fn get_master_channel_group(&self) -> Result<channel_group::ChannelGroup, ::Status> {
         let mut channel_group = ::std::ptr::null_mut();
 
@@ -2038,6 +2137,15 @@ impl Sys {
         }
     }
 
+    /// Walks the DSP network from [`get_DSP_head`](#method.get_DSP_head) and returns it as a
+    /// graph, which can be exported to Graphviz DOT or JSON.
+    pub fn dsp_graph(&self) -> Result<dsp_graph::DspGraph, ::Status> {
+        match self.get_DSP_head() {
+            Ok(head) => Ok(dsp_graph::from_head(self, head)),
+            Err(e) => Err(e)
+        }
+    }
+
     pub fn add_DSP(&self, dsp: &dsp::Dsp) -> Result<dsp_connection::DspConnection, ::Status> {
         let mut t_connection = ::std::ptr::null_mut();
 
@@ -2165,6 +2273,46 @@ impl Sys {
         }
     }
 
+    /// Creates a geometry from data returned by
+    /// [`Geometry::save`](struct.Geometry.html#method.save).
+    pub fn load_geometry(&self, data: &[u8]) -> Result<geometry::Geometry, ::Status> {
+        let mut geometry = ::std::ptr::null_mut();
+
+        match unsafe { ffi::FMOD_System_LoadGeometry(self.system,
+                                                     data.as_ptr() as *mut c_void,
+                                                     data.len() as c_int, &mut geometry) } {
+            ::Status::Ok => Ok(ffi::FFI::wrap(geometry)),
+            e => Err(e),
+        }
+    }
+
+    /// Loads a geometry saved with
+    /// [`Geometry::save_to_file`](struct.Geometry.html#method.save_to_file). I/O errors are
+    /// returned as `RStatus::Other`.
+    pub fn load_geometry_from_file(&self, path: &str) -> Result<geometry::Geometry, ::RStatus> {
+        let mut data = Vec::new();
+
+        if let Err(e) = std::fs::File::open(path).and_then(|mut file| file.read_to_end(&mut data)) {
+            return Err(::RStatus::Other(format!("{}: {}", path, e)));
+        }
+        match self.load_geometry(&data) {
+            Ok(g) => Ok(g),
+            Err(e) => Err(::RStatus::FMOD(e))
+        }
+    }
+
+    /// Creates a geometry from the faces of a Wavefront OBJ file. Parse errors are returned as
+    /// `RStatus::Other` with their line number.
+    pub fn geometry_from_obj<R: Read>(&self, reader: R,
+                                      map: &geometry_obj::MaterialMap)
+                                      -> Result<geometry::Geometry, ::RStatus> {
+        match geometry_obj::parse_obj(reader, map) {
+            Ok(polygons) => geometry_obj::create_geometry(self, &polygons, map.max_polygons,
+                                                          map.max_vertices),
+            Err(e) => Err(e)
+        }
+    }
+
     pub fn set_geometry_settings(&self, max_world_size: f32) -> ::Status {
         unsafe { ffi::FMOD_System_SetGeometrySettings(self.system, max_world_size) }
     }
@@ -2178,20 +2326,22 @@ impl Sys {
         }
     }
 
+    /// Computes the occlusion between a listener and a source at the given positions, through
+    /// every active geometry.
+    ///
     /// Returns:
     ///
-    /// Ok(listener, source, direct, reverb)
-    pub fn get_geometry_occlusion(&self)
-                                  -> Result<(vector::Vector, vector::Vector, f32, f32), ::Status> {
-        let listener = vector::get_ffi(&vector::Vector::new());
-        let source = vector::get_ffi(&vector::Vector::new());
+    /// Ok(direct, reverb)
+    pub fn get_geometry_occlusion(&self, listener: &vector::Vector,
+                                  source: &vector::Vector) -> Result<(f32, f32), ::Status> {
+        let listener = vector::get_ffi(listener);
+        let source = vector::get_ffi(source);
         let mut direct = 0f32;
         let mut reverb = 0f32;
 
         match unsafe { ffi::FMOD_System_GetGeometryOcclusion(self.system, &listener, &source,
                                                              &mut direct, &mut reverb) } {
-            ::Status::Ok => Ok((vector::from_ptr(listener),
-                                vector::from_ptr(source), direct, reverb)),
+            ::Status::Ok => Ok((direct, reverb)),
             e => Err(e),
         }
     }
@@ -2212,6 +2362,51 @@ impl Sys {
         }
     }
 
+    /// Plays the sound and applies a state read with
+    /// [`Channel::snapshot`](struct.Channel.html#method.snapshot). The sound starts paused and is
+    /// only resumed once everything has been restored.
+    ///
+    /// Returns `Err(Status::InvalidParam)` if the channel group of the state cannot be found.
+    pub fn restore_channel(&self, sound: &Sound,
+                           state: &channel::ChannelState) -> Result<channel::Channel, ::Status> {
+        let group = match state.channel_group {
+            Some(ref name) => {
+                let master = self.get_master_channel_group()?;
+
+                match find_channel_group(master, name) {
+                    Some(g) => Some(g),
+                    None => return Err(::Status::InvalidParam)
+                }
+            }
+            None => None
+        };
+        let mut t_channel = ::std::ptr::null_mut();
+
+        match unsafe { ffi::FMOD_System_PlaySound(self.system, ::ChannelIndex::Free,
+                                                  ffi::FFI::unwrap(sound), 1, &mut t_channel) } {
+            ::Status::Ok => {}
+            e => return Err(e)
+        }
+
+        let mut channel : channel::Channel = ffi::FFI::wrap(t_channel);
+        let status = match group {
+            Some(ref g) => channel.set_channel_group(g),
+            None => ::Status::Ok
+        };
+
+        match if status == ::Status::Ok {
+            channel.apply_state(state)
+        } else {
+            status
+        } {
+            ::Status::Ok => Ok(channel),
+            e => {
+                channel.stop();
+                Err(e)
+            }
+        }
+    }
+
     pub fn set_file_system(&self, user_open: FileOpenCallback, user_close: FileCloseCallback,
                            user_read: FileReadCallback, user_seek: FileSeekCallback,/*
                            user_async_read: ffi::FMOD_FILE_ASYNCREADCALLBACK,
@@ -30,6 +30,8 @@ use fmod_sys;
 use fmod_sys::MemoryUsageDetails;
 use std::mem::transmute;
 use std::default::Default;
+use std::fs::File;
+use std::io::Write;
 
 /// Geometry object
 pub struct Geometry {
@@ -52,6 +54,201 @@ impl Drop for Geometry {
     }
 }
 
+/// Occlusion attributes given to the polygons of a [`Geometry`](struct.Geometry.html).
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct OcclusionMaterial {
+    /// 0.0 lets the direct sound through, 1.0 blocks it.
+    pub direct_occlusion: f32,
+    /// 0.0 lets the reverb through, 1.0 blocks it.
+    pub reverb_occlusion: f32,
+    pub double_sided    : bool,
+}
+
+impl Default for OcclusionMaterial {
+    fn default() -> OcclusionMaterial {
+        OcclusionMaterial::new(1f32, 1f32, false)
+    }
+}
+
+impl OcclusionMaterial {
+    pub fn new(direct_occlusion: f32, reverb_occlusion: f32,
+               double_sided: bool) -> OcclusionMaterial {
+        OcclusionMaterial {
+            direct_occlusion: direct_occlusion,
+            reverb_occlusion: reverb_occlusion,
+            double_sided: double_sided,
+        }
+    }
+}
+
+/// Adds the polygons to the geometry, each with its material.
+pub fn add_polygons(geometry: &Geometry,
+                    polygons: &[(OcclusionMaterial, Vec<vector::Vector>)]) -> ::Status {
+    for (material, vertices) in polygons.iter() {
+        if let Err(e) = geometry.add_polygon(material.direct_occlusion, material.reverb_occlusion,
+                                             material.double_sided, vertices.clone()) {
+            return e;
+        }
+    }
+    ::Status::Ok
+}
+
+/// Creates a geometry just big enough for the polygons and adds them.
+pub fn create_geometry(sys: &fmod_sys::Sys,
+                       polygons: &[(OcclusionMaterial, Vec<vector::Vector>)])
+                       -> Result<Geometry, ::Status> {
+    let (num_polygons, num_vertices) = get_size(polygons);
+    let geometry = sys.create_geometry(::std::cmp::max(num_polygons, 1),
+                                       ::std::cmp::max(num_vertices, 1))?;
+
+    match add_polygons(&geometry, polygons) {
+        ::Status::Ok => Ok(geometry),
+        e => Err(e)
+    }
+}
+
+/// Returns the (max_polygons, max_vertices) needed to hold the polygons.
+pub fn get_size(polygons: &[(OcclusionMaterial, Vec<vector::Vector>)]) -> (i32, i32) {
+    (polygons.len() as i32, polygons.iter().map(|p| p.1.len()).sum::<usize>() as i32)
+}
+
+/// A polygon of a [`Geometry`](struct.Geometry.html), copied out of FMOD. Changes are applied
+/// with [`Geometry::update_polygons`](struct.Geometry.html#method.update_polygons).
+#[derive(Debug, PartialEq, Clone)]
+pub struct Polygon {
+    pub index   : i32,
+    /// Vertices in the local space of the geometry.
+    pub vertices: Vec<vector::Vector>,
+    pub material: OcclusionMaterial,
+}
+
+fn read_polygon(geometry: &Geometry, index: i32) -> Result<Polygon, ::Status> {
+    let material = match geometry.get_polygon_attributes(index) {
+        Ok((direct, reverb, double_sided)) => OcclusionMaterial::new(direct, reverb, double_sided),
+        Err(e) => return Err(e)
+    };
+    let num_vertices = geometry.get_polygon_num_vertices(index)?;
+    let mut vertices = Vec::with_capacity(num_vertices as usize);
+
+    for vertex in 0..num_vertices {
+        match geometry.get_polygon_vertex(index, vertex) {
+            Ok(v) => vertices.push(v),
+            Err(e) => return Err(e)
+        }
+    }
+    Ok(Polygon {
+        index: index,
+        vertices: vertices,
+        material: material,
+    })
+}
+
+/// Iterator over the polygons of a [`Geometry`](struct.Geometry.html), returned by
+/// [`Geometry::polygons`](struct.Geometry.html#method.polygons).
+///
+/// A polygon FMOD fails to read is yielded as an error, the iteration then goes on with the next
+/// one. If the number of polygons can't be read, its error is the only item.
+pub struct Polygons<'a> {
+    geometry: &'a Geometry,
+    index: i32,
+    count: i32,
+    error: Option<::Status>,
+}
+
+impl<'a> Iterator for Polygons<'a> {
+    type Item = Result<Polygon, ::Status>;
+
+    fn next(&mut self) -> Option<Result<Polygon, ::Status>> {
+        if let Some(e) = self.error.take() {
+            return Some(Err(e));
+        }
+        if self.index < self.count {
+            let index = self.index;
+
+            self.index += 1;
+            Some(read_polygon(self.geometry, index))
+        } else {
+            None
+        }
+    }
+}
+
+/// Editable view of a polygon of a [`Geometry`](struct.Geometry.html), returned by
+/// [`Geometry::polygon_mut`](struct.Geometry.html#method.polygon_mut). Every change goes to
+/// FMOD right away. The number of vertices of a polygon can't change.
+pub struct PolygonMut<'a> {
+    geometry: &'a Geometry,
+    index: i32,
+    num_vertices: i32,
+}
+
+impl<'a> PolygonMut<'a> {
+    pub fn get_index(&self) -> i32 {
+        self.index
+    }
+
+    pub fn get_num_vertices(&self) -> i32 {
+        self.num_vertices
+    }
+
+    pub fn get(&self) -> Result<Polygon, ::Status> {
+        read_polygon(self.geometry, self.index)
+    }
+
+    pub fn get_vertex(&self, vertex: i32) -> Result<vector::Vector, ::Status> {
+        self.geometry.get_polygon_vertex(self.index, vertex)
+    }
+
+    pub fn set_vertex(&mut self, vertex: i32, position: vector::Vector) -> ::Status {
+        self.geometry.set_polygon_vertex(self.index, vertex, position)
+    }
+
+    /// Replaces every vertex. Returns `Status::InvalidParam` if the number of vertices differs.
+    pub fn set_vertices(&mut self, vertices: &[vector::Vector]) -> ::Status {
+        if vertices.len() != self.num_vertices as usize {
+            return ::Status::InvalidParam;
+        }
+        for (vertex, position) in vertices.iter().enumerate() {
+            match self.set_vertex(vertex as i32, *position) {
+                ::Status::Ok => {}
+                e => return e
+            }
+        }
+        ::Status::Ok
+    }
+
+    /// Moves every vertex by `offset`.
+    pub fn translate(&mut self, offset: &vector::Vector) -> ::Status {
+        for vertex in 0..self.num_vertices {
+            let position = match self.get_vertex(vertex) {
+                Ok(p) => p,
+                Err(e) => return e
+            };
+
+            match self.set_vertex(vertex, vector::add(&position, offset)) {
+                ::Status::Ok => {}
+                e => return e
+            }
+        }
+        ::Status::Ok
+    }
+
+    pub fn get_material(&self) -> Result<OcclusionMaterial, ::Status> {
+        match self.geometry.get_polygon_attributes(self.index) {
+            Ok((direct, reverb, double_sided)) => {
+                Ok(OcclusionMaterial::new(direct, reverb, double_sided))
+            }
+            Err(e) => Err(e)
+        }
+    }
+
+    pub fn set_material(&mut self, material: &OcclusionMaterial) -> ::Status {
+        self.geometry.set_polygon_attributes(self.index, material.direct_occlusion,
+                                             material.reverb_occlusion, material.double_sided)
+    }
+}
+
 impl Geometry {
     pub fn release(&mut self) -> ::Status {
         if self.geometry !=::std::ptr::null_mut() {
@@ -67,6 +264,8 @@ impl Geometry {
         }
     }
 
+    /// Adds a polygon and returns its index. The vertices are in the local space of the
+    /// geometry; FMOD expects at least 3 of them, coplanar and forming a convex polygon.
     pub fn add_polygon(&self, direct_occlusion: f32, reverb_occlusion: f32, double_sided: bool,
                        vertices: Vec<vector::Vector>) -> Result<i32, ::Status> {
         let t_double_sided = if double_sided == true {
@@ -75,11 +274,7 @@ impl Geometry {
             0
         };
         let mut index = 0i32;
-        let t_vertices = Vec::with_capacity(vertices.len());
-
-        for tmp in vertices.iter() {
-            vector::get_ffi(tmp);
-        }
+        let t_vertices : Vec<ffi::FMOD_VECTOR> = vertices.iter().map(vector::get_ffi).collect();
 
         match unsafe { ffi::FMOD_Geometry_AddPolygon(self.geometry, direct_occlusion,
                                                      reverb_occlusion, t_double_sided,
@@ -99,6 +294,86 @@ impl Geometry {
         }
     }
 
+    /// Iterates over the polygons, with their vertices and attributes. Read errors are yielded,
+    /// not skipped:
+    ///
+    /// ```ignore
+    /// let polygons = geometry.polygons().collect::<Result<Vec<_>, _>>().unwrap();
+    /// ```
+    pub fn polygons(&self) -> Polygons<'_> {
+        let (count, error) = match self.get_num_polygons() {
+            Ok(count) => (count, None),
+            Err(e) => (0, Some(e))
+        };
+
+        Polygons {
+            geometry: self,
+            index: 0,
+            count: count,
+            error: error,
+        }
+    }
+
+    /// Returns an editable view of a polygon.
+    pub fn polygon_mut(&mut self, index: i32) -> Result<PolygonMut<'_>, ::Status> {
+        match self.get_polygon_num_vertices(index) {
+            Ok(num_vertices) => Ok(PolygonMut {
+                geometry: self,
+                index: index,
+                num_vertices: num_vertices,
+            }),
+            Err(e) => Err(e)
+        }
+    }
+
+    /// Writes back the vertices and attributes of polygons read with
+    /// [`polygons`](#method.polygons), matched by their index. Stops at the first error.
+    pub fn update_polygons(&mut self, polygons: &[Polygon]) -> ::Status {
+        for polygon in polygons.iter() {
+            let mut view = match self.polygon_mut(polygon.index) {
+                Ok(v) => v,
+                Err(e) => return e
+            };
+
+            match view.set_vertices(&polygon.vertices) {
+                ::Status::Ok => {}
+                e => return e
+            }
+            match view.set_material(&polygon.material) {
+                ::Status::Ok => {}
+                e => return e
+            }
+        }
+        ::Status::Ok
+    }
+
+    /// Moves every vertex of the given polygons by `offset`, such as all the polygons of a door.
+    pub fn translate_polygons(&mut self, indices: &[i32], offset: &vector::Vector) -> ::Status {
+        for index in indices.iter() {
+            match self.polygon_mut(*index) {
+                Ok(mut view) => match view.translate(offset) {
+                    ::Status::Ok => {}
+                    e => return e
+                },
+                Err(e) => return e
+            }
+        }
+        ::Status::Ok
+    }
+
+    /// Gives the same occlusion material to the given polygons.
+    pub fn set_polygons_material(&mut self, indices: &[i32],
+                                 material: &OcclusionMaterial) -> ::Status {
+        for index in indices.iter() {
+            match self.set_polygon_attributes(*index, material.direct_occlusion,
+                                              material.reverb_occlusion, material.double_sided) {
+                ::Status::Ok => {}
+                e => return e
+            }
+        }
+        ::Status::Ok
+    }
+
     pub fn get_max_polygons(&self) -> Result<(i32, i32), ::Status> {
         let mut max_polygons = 0i32;
         let mut max_vertices = 0i32;
@@ -236,6 +511,40 @@ impl Geometry {
         }
     }
 
+    /// Serializes the polygons and attributes of the geometry. The data can be loaded back with
+    /// [`Sys::load_geometry`](struct.Sys.html#method.load_geometry).
+    pub fn save(&self) -> Result<Vec<u8>, ::Status> {
+        let mut size = 0 as c_int;
+
+        match unsafe { ffi::FMOD_Geometry_Save(self.geometry, ::std::ptr::null_mut(), &mut size) } {
+            ::Status::Ok => {}
+            e => return Err(e)
+        }
+        let mut data = vec![0u8; size as usize];
+
+        match unsafe { ffi::FMOD_Geometry_Save(self.geometry, data.as_mut_ptr() as *mut c_void,
+                                               &mut size) } {
+            ::Status::Ok => {
+                data.truncate(size as usize);
+                Ok(data)
+            }
+            e => Err(e)
+        }
+    }
+
+    /// Saves the geometry to a file. I/O errors are returned as `RStatus::Other`.
+    pub fn save_to_file(&self, path: &str) -> Result<(), ::RStatus> {
+        let data = match self.save() {
+            Ok(d) => d,
+            Err(e) => return Err(::RStatus::FMOD(e))
+        };
+
+        match File::create(path).and_then(|mut file| file.write_all(&data)) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(::RStatus::Other(format!("{}: {}", path, e)))
+        }
+    }
+
     /// Returns:
     ///
     /// Ok(memory_used, details)
@@ -0,0 +1,442 @@
+/*
+* Rust-FMOD - Copyright (c) 2018 Gomez Guillaume.
+*
+* The Original software, FmodEx library, is provided by FIRELIGHT TECHNOLOGIES.
+*
+* This software is provided 'as-is', without any express or implied warranty.
+* In no event will the authors be held liable for any damages arising from
+* the use of this software.
+*
+* Permission is granted to anyone to use this software for any purpose,
+* including commercial applications, and to alter it and redistribute it
+* freely, subject to the following restrictions:
+*
+* 1. The origin of this software must not be misrepresented; you must not claim
+*    that you wrote the original software. If you use this software in a product,
+*    an acknowledgment in the product documentation would be appreciated but is
+*    not required.
+*
+* 2. Altered source versions must be plainly marked as such, and must not be
+*    misrepresented as being the original software.
+*
+* 3. This notice may not be removed or altered from any source distribution.
+*/
+
+use vector;
+use vector::Vector;
+use geometry;
+use geometry::{Geometry, OcclusionMaterial};
+use fmod_sys::Sys;
+
+/// Position, orientation and scale, with FMOD's left-handed axes: x right, y up and z forward.
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GeometryTransform {
+    pub position: Vector,
+    pub forward : Vector,
+    pub up      : Vector,
+    pub scale   : Vector,
+}
+
+impl Default for GeometryTransform {
+    fn default() -> GeometryTransform {
+        GeometryTransform {
+            position: Vector::new(),
+            forward: Vector { x: 0f32, y: 0f32, z: 1f32 },
+            up: Vector { x: 0f32, y: 1f32, z: 0f32 },
+            scale: Vector { x: 1f32, y: 1f32, z: 1f32 },
+        }
+    }
+}
+
+fn normalize(v: &Vector) -> Vector {
+    let length = vector::dot(v, v).sqrt();
+
+    if length > 0f32 {
+        vector::scale(v, 1f32 / length)
+    } else {
+        *v
+    }
+}
+
+impl GeometryTransform {
+    /// Returns an identity transform moved to the given position.
+    pub fn at(position: Vector) -> GeometryTransform {
+        GeometryTransform {
+            position: position,
+            .. Default::default()
+        }
+    }
+
+    pub fn rotated(mut self, forward: Vector, up: Vector) -> GeometryTransform {
+        self.forward = forward;
+        self.up = up;
+        self
+    }
+
+    pub fn scaled(mut self, scale: Vector) -> GeometryTransform {
+        self.scale = scale;
+        self
+    }
+
+    /// Returns the point in the parent space.
+    pub fn apply(&self, point: &Vector) -> Vector {
+        let forward = normalize(&self.forward);
+        let up = normalize(&self.up);
+        let right = vector::cross(&up, &forward);
+        let mut result = self.position;
+
+        result = vector::add(&result, &vector::scale(&right, point.x * self.scale.x));
+        result = vector::add(&result, &vector::scale(&up, point.y * self.scale.y));
+        vector::add(&result, &vector::scale(&forward, point.z * self.scale.z))
+    }
+}
+
+/// A wall of a [`Primitive::Room`](enum.Primitive.html).
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum RoomWall {
+    NegativeX,
+    PositiveX,
+    NegativeZ,
+    PositiveZ,
+}
+
+/// An opening of a [`Primitive::Room`](enum.Primitive.html), starting from the floor.
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Door {
+    pub wall  : RoomWall,
+    /// Position of the center of the door from the center of the wall, along z for the x walls
+    /// and along x for the z walls.
+    pub offset: f32,
+    pub width : f32,
+    pub height: f32,
+}
+
+/// Shapes handled by a [`GeometryBuilder`](struct.GeometryBuilder.html), in their local space.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Primitive {
+    /// A closed box centered on the origin, faces pointing outward. Boxes are oriented with the
+    /// rotation of their transform.
+    Box {
+        size: Vector,
+    },
+    /// A hollow box centered on the origin, faces pointing inward, with openings in its walls.
+    Room {
+        size: Vector,
+        doors: Vec<Door>,
+    },
+    /// A polyline on the floor, extruded upward by `height`.
+    Wall {
+        points: Vec<Vector>,
+        height: f32,
+    },
+    /// A planar quad.
+    Quad {
+        corners: [Vector; 4],
+    },
+}
+
+fn rectangle(center: &Vector, u: &Vector, v: &Vector) -> Vec<Vector> {
+    vec![vector::sub(&vector::sub(center, u), v), vector::sub(&vector::add(center, u), v),
+         vector::add(&vector::add(center, u), v), vector::add(&vector::sub(center, u), v)]
+}
+
+fn axis(x: f32, y: f32, z: f32) -> Vector {
+    Vector { x: x, y: y, z: z }
+}
+
+/// Rejects NaN and infinite sizes along with the negative ones.
+fn is_positive(value: f32) -> bool {
+    value.is_finite() && value > 0f32
+}
+
+fn is_valid_size(size: &Vector) -> bool {
+    is_positive(size.x) && is_positive(size.y) && is_positive(size.z)
+}
+
+/// Returns the faces of a box: +x, -x, +y, -y, +z, -z. They point outward, or inward if
+/// `inward` is true.
+fn box_faces(size: &Vector, inward: bool) -> Vec<Vec<Vector>> {
+    let (hx, hy, hz) = (size.x / 2f32, size.y / 2f32, size.z / 2f32);
+    // (center, u, v) with u x v pointing outward
+    let faces = [(axis(hx, 0f32, 0f32), axis(0f32, hy, 0f32), axis(0f32, 0f32, hz)),
+                 (axis(-hx, 0f32, 0f32), axis(0f32, 0f32, hz), axis(0f32, hy, 0f32)),
+                 (axis(0f32, hy, 0f32), axis(0f32, 0f32, hz), axis(hx, 0f32, 0f32)),
+                 (axis(0f32, -hy, 0f32), axis(hx, 0f32, 0f32), axis(0f32, 0f32, hz)),
+                 (axis(0f32, 0f32, hz), axis(hx, 0f32, 0f32), axis(0f32, hy, 0f32)),
+                 (axis(0f32, 0f32, -hz), axis(0f32, hy, 0f32), axis(hx, 0f32, 0f32))];
+
+    faces.iter().map(|(center, u, v)| {
+        let mut face = rectangle(center, u, v);
+
+        if inward {
+            face.reverse();
+        }
+        face
+    }).collect()
+}
+
+/// Splits a wall of a room around its doors. Returns `None` if a door doesn't fit.
+fn room_wall(size: &Vector, wall: RoomWall, doors: &[&Door]) -> Option<Vec<Vec<Vector>>> {
+    let (hx, hy, hz) = (size.x / 2f32, size.y / 2f32, size.z / 2f32);
+    // center of the wall, axis along which the offsets are measured and half length
+    let (center, along, half) = match wall {
+        RoomWall::PositiveX => (axis(hx, 0f32, 0f32), axis(0f32, 0f32, 1f32), hz),
+        RoomWall::NegativeX => (axis(-hx, 0f32, 0f32), axis(0f32, 0f32, 1f32), hz),
+        RoomWall::PositiveZ => (axis(0f32, 0f32, hz), axis(1f32, 0f32, 0f32), hx),
+        RoomWall::NegativeZ => (axis(0f32, 0f32, -hz), axis(1f32, 0f32, 0f32), hx),
+    };
+    let up = axis(0f32, 1f32, 0f32);
+    let inward = vector::scale(&center, -1f32);
+    let rect = |t0: f32, t1: f32, y0: f32, y1: f32| {
+        let mut face = vec![vector::add(&vector::add(&center, &vector::scale(&along, t0)),
+                                        &vector::scale(&up, y0)),
+                            vector::add(&vector::add(&center, &vector::scale(&along, t1)),
+                                        &vector::scale(&up, y0)),
+                            vector::add(&vector::add(&center, &vector::scale(&along, t1)),
+                                        &vector::scale(&up, y1)),
+                            vector::add(&vector::add(&center, &vector::scale(&along, t0)),
+                                        &vector::scale(&up, y1))];
+
+        if vector::dot(&vector::cross(&along, &up), &inward) < 0f32 {
+            face.reverse();
+        }
+        face
+    };
+    let mut doors = doors.to_vec();
+    let mut faces = Vec::new();
+    let mut start = -half;
+
+    doors.sort_by(|a, b| a.offset.partial_cmp(&b.offset).unwrap_or(::std::cmp::Ordering::Equal));
+    for door in doors.iter() {
+        let (left, right) = (door.offset - door.width / 2f32, door.offset + door.width / 2f32);
+
+        if !is_positive(door.width) || !is_positive(door.height) || door.height > size.y
+           || left < start || right > half {
+            return None;
+        }
+        if left > start {
+            faces.push(rect(start, left, -hy, hy));
+        }
+        if door.height < size.y {
+            faces.push(rect(left, right, -hy + door.height, hy));
+        }
+        start = right;
+    }
+    if start < half {
+        faces.push(rect(start, half, -hy, hy));
+    }
+    Some(faces)
+}
+
+impl Primitive {
+    /// Returns the polygons of the primitive in its local space, or `None` if it is invalid.
+    pub fn get_polygons(&self) -> Option<Vec<Vec<Vector>>> {
+        match *self {
+            Primitive::Box { ref size } => {
+                if !is_valid_size(size) {
+                    return None;
+                }
+                Some(box_faces(size, false))
+            }
+            Primitive::Room { ref size, ref doors } => {
+                if !is_valid_size(size) || doors.iter().any(|d| !d.offset.is_finite()) {
+                    return None;
+                }
+                // ceiling and floor
+                let mut polygons : Vec<Vec<Vector>> = box_faces(size, true).drain(2..4).collect();
+
+                for wall in [RoomWall::PositiveX, RoomWall::NegativeX, RoomWall::PositiveZ,
+                             RoomWall::NegativeZ].iter() {
+                    let wall_doors : Vec<&Door> = doors.iter().filter(|d| d.wall == *wall)
+                                                       .collect();
+
+                    match room_wall(size, *wall, &wall_doors) {
+                        Some(faces) => polygons.extend(faces),
+                        None => return None
+                    }
+                }
+                Some(polygons)
+            }
+            Primitive::Wall { ref points, height } => {
+                if points.len() < 2 || !is_positive(height) {
+                    return None;
+                }
+                let up = axis(0f32, height, 0f32);
+
+                Some(points.windows(2).map(|segment| {
+                    vec![segment[0], segment[1], vector::add(&segment[1], &up),
+                         vector::add(&segment[0], &up)]
+                }).collect())
+            }
+            Primitive::Quad { ref corners } => Some(vec![corners.to_vec()])
+        }
+    }
+}
+
+/// Builds a [`Geometry`](struct.Geometry.html) from primitives, each with its own occlusion
+/// material and local transform.
+///
+/// ```ignore
+/// let wall = OcclusionMaterial::new(0.8, 0.5, true);
+/// let geometry = GeometryBuilder::new()
+///     .add(Primitive::Room {
+///         size: Vector { x: 10f32, y: 3f32, z: 8f32 },
+///         doors: vec![Door { wall: RoomWall::PositiveX, offset: 0f32, width: 1f32,
+///                            height: 2f32 }],
+///     }, wall, GeometryTransform::default())
+///     .add(Primitive::Box { size: Vector { x: 1f32, y: 2f32, z: 1f32 } }, wall,
+///          GeometryTransform::at(Vector { x: 2f32, y: 0f32, z: 0f32 }))
+///     .build(&sys).unwrap();
+/// ```
+///
+/// The transform given to [`transform`](#method.transform) is applied to the whole geometry with
+/// [`Geometry::set_position`](struct.Geometry.html#method.set_position),
+/// [`set_rotation`](struct.Geometry.html#method.set_rotation) and
+/// [`set_scale`](struct.Geometry.html#method.set_scale), so it can be moved later without
+/// rebuilding it.
+#[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct GeometryBuilder {
+    pub transform : GeometryTransform,
+    pub primitives: Vec<(Primitive, OcclusionMaterial, GeometryTransform)>,
+}
+
+impl GeometryBuilder {
+    pub fn new() -> GeometryBuilder {
+        Default::default()
+    }
+
+    pub fn transform(mut self, transform: GeometryTransform) -> GeometryBuilder {
+        self.transform = transform;
+        self
+    }
+
+    pub fn add(mut self, primitive: Primitive, material: OcclusionMaterial,
+               transform: GeometryTransform) -> GeometryBuilder {
+        self.primitives.push((primitive, material, transform));
+        self
+    }
+
+    /// Returns every polygon in the space of the geometry, or `Err(Status::InvalidParam)` if a
+    /// primitive is invalid, such as a door not fitting in its wall.
+    pub fn get_polygons(&self) -> Result<Vec<(OcclusionMaterial, Vec<Vector>)>, ::Status> {
+        let mut polygons = Vec::new();
+
+        for &(ref primitive, material, ref transform) in self.primitives.iter() {
+            match primitive.get_polygons() {
+                Some(p) => polygons.extend(p.into_iter().map(|polygon| {
+                    (material, polygon.iter().map(|v| transform.apply(v)).collect())
+                })),
+                None => return Err(::Status::InvalidParam)
+            }
+        }
+        Ok(polygons)
+    }
+
+    /// Returns the (max_polygons, max_vertices) needed by
+    /// [`Sys::create_geometry`](struct.Sys.html#method.create_geometry).
+    pub fn get_size(&self) -> Result<(i32, i32), ::Status> {
+        self.get_polygons().map(|polygons| geometry::get_size(&polygons))
+    }
+
+    pub fn build(&self, sys: &Sys) -> Result<Geometry, ::Status> {
+        let polygons = self.get_polygons()?;
+        let geometry = geometry::create_geometry(sys, &polygons)?;
+
+        match geometry.set_position(self.transform.position) {
+            ::Status::Ok => {}
+            e => return Err(e)
+        }
+        match geometry.set_rotation(self.transform.forward, self.transform.up) {
+            ::Status::Ok => {}
+            e => return Err(e)
+        }
+        match geometry.set_scale(self.transform.scale) {
+            ::Status::Ok => Ok(geometry),
+            e => Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn room(doors: Vec<Door>) -> Primitive {
+        Primitive::Room { size: axis(4f32, 3f32, 6f32), doors: doors }
+    }
+
+    fn door(wall: RoomWall, offset: f32, width: f32, height: f32) -> Door {
+        Door { wall: wall, offset: offset, width: width, height: height }
+    }
+
+    fn area(polygons: &[Vec<Vector>]) -> f32 {
+        polygons.iter().map(|p| {
+            let normal = vector::polygon_normal(p);
+
+            vector::dot(&normal, &normal).sqrt() / 2f32
+        }).sum()
+    }
+
+    #[test]
+    fn closed_room() {
+        let polygons = room(Vec::new()).get_polygons().unwrap();
+
+        // floor, ceiling and 4 walls, facing the center
+        assert_eq!(polygons.len(), 6);
+        assert!((area(&polygons) - 2f32 * (4f32 * 3f32 + 6f32 * 3f32 + 4f32 * 6f32)).abs() < 1e-4);
+        for polygon in polygons.iter() {
+            let normal = vector::polygon_normal(polygon);
+
+            assert!(vector::dot(&normal, &polygon[0]) < 0f32);
+        }
+    }
+
+    #[test]
+    fn door_splitting() {
+        let closed = area(&room(Vec::new()).get_polygons().unwrap());
+        // a door in the middle leaves a wall on each side and a lintel above it
+        let one = room(vec![door(RoomWall::PositiveX, 0f32, 1f32, 2f32)]).get_polygons().unwrap();
+        // a door against the corner and another one as tall as the wall leave no lintel
+        let two = room(vec![door(RoomWall::NegativeZ, 1.5f32, 1f32, 3f32),
+                            door(RoomWall::NegativeZ, -1f32, 1f32, 2f32)]).get_polygons().unwrap();
+
+        assert_eq!(one.len(), 6 - 1 + 3);
+        assert!((closed - area(&one) - 2f32).abs() < 1e-4);
+        assert_eq!(two.len(), 6 - 1 + 3);
+        assert!((closed - area(&two) - 5f32).abs() < 1e-4);
+        // doors touching each other are fine
+        assert!(room(vec![door(RoomWall::PositiveZ, -0.5f32, 1f32, 2f32),
+                          door(RoomWall::PositiveZ, 0.5f32, 1f32, 2f32)]).get_polygons()
+                                                                        .is_some());
+    }
+
+    #[test]
+    fn rejected_doors() {
+        let rejected = |doors: Vec<Door>| room(doors).get_polygons().is_none();
+
+        // overlapping, whatever their order
+        assert!(rejected(vec![door(RoomWall::PositiveX, 0f32, 2f32, 2f32),
+                              door(RoomWall::PositiveX, 0.5f32, 1f32, 2f32)]));
+        assert!(rejected(vec![door(RoomWall::PositiveX, 0.5f32, 1f32, 2f32),
+                              door(RoomWall::PositiveX, 0f32, 2f32, 2f32)]));
+        // out of the wall, which is 6 long for the x walls and 4 long for the z walls
+        assert!(rejected(vec![door(RoomWall::NegativeX, 2.75f32, 1f32, 2f32)]));
+        assert!(rejected(vec![door(RoomWall::PositiveZ, 1.75f32, 1f32, 2f32)]));
+        assert!(rejected(vec![door(RoomWall::PositiveZ, 0f32, 1f32, 3.5f32)]));
+        // empty or not a number
+        assert!(rejected(vec![door(RoomWall::PositiveZ, 0f32, 0f32, 2f32)]));
+        assert!(rejected(vec![door(RoomWall::PositiveZ, 0f32, f32::NAN, 2f32)]));
+        assert!(rejected(vec![door(RoomWall::PositiveZ, 0f32, 1f32, f32::NAN)]));
+        assert!(rejected(vec![door(RoomWall::PositiveZ, f32::NAN, 1f32, 2f32)]));
+        assert!(rejected(vec![door(RoomWall::PositiveZ, 0f32, f32::INFINITY, 2f32)]));
+        // doors on different walls don't overlap
+        assert!(!rejected(vec![door(RoomWall::PositiveX, 0f32, 2f32, 2f32),
+                               door(RoomWall::NegativeX, 0.5f32, 1f32, 2f32)]));
+    }
+}
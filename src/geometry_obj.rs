@@ -0,0 +1,393 @@
+/*
+* Rust-FMOD - Copyright (c) 2018 Gomez Guillaume.
+*
+* The Original software, FmodEx library, is provided by FIRELIGHT TECHNOLOGIES.
+*
+* This software is provided 'as-is', without any express or implied warranty.
+* In no event will the authors be held liable for any damages arising from
+* the use of this software.
+*
+* Permission is granted to anyone to use this software for any purpose,
+* including commercial applications, and to alter it and redistribute it
+* freely, subject to the following restrictions:
+*
+* 1. The origin of this software must not be misrepresented; you must not claim
+*    that you wrote the original software. If you use this software in a product,
+*    an acknowledgment in the product documentation would be appreciated but is
+*    not required.
+*
+* 2. Altered source versions must be plainly marked as such, and must not be
+*    misrepresented as being the original software.
+*
+* 3. This notice may not be removed or altered from any source distribution.
+*/
+
+use vector;
+use vector::Vector;
+use geometry;
+use geometry::{Geometry, OcclusionMaterial};
+use fmod_sys::Sys;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read};
+
+/// Tells [`Sys::geometry_from_obj`](struct.Sys.html#method.geometry_from_obj) how to turn the
+/// faces of an OBJ file into polygons.
+///
+/// A face uses the material named by the last `usemtl` statement if it is in the map, otherwise
+/// the first of its `g` or `o` names found in the map, otherwise the default material.
+#[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct MaterialMap {
+    pub default     : OcclusionMaterial,
+    /// Materials by material, group or object name.
+    pub materials   : HashMap<String, OcclusionMaterial>,
+    /// Splits every face into triangles. Otherwise convex faces are kept as they are and only
+    /// concave ones are split.
+    pub triangulate : bool,
+    /// Fails if the file needs more polygons.
+    pub max_polygons: Option<i32>,
+    /// Fails if the file needs more vertices, counted over every polygon.
+    pub max_vertices: Option<i32>,
+}
+
+impl MaterialMap {
+    pub fn new() -> MaterialMap {
+        Default::default()
+    }
+
+    pub fn default_material(mut self, material: OcclusionMaterial) -> MaterialMap {
+        self.default = material;
+        self
+    }
+
+    pub fn material(mut self, name: &str, material: OcclusionMaterial) -> MaterialMap {
+        self.materials.insert(name.to_owned(), material);
+        self
+    }
+
+    pub fn triangulate(mut self, triangulate: bool) -> MaterialMap {
+        self.triangulate = triangulate;
+        self
+    }
+
+    pub fn limits(mut self, max_polygons: i32, max_vertices: i32) -> MaterialMap {
+        self.max_polygons = Some(max_polygons);
+        self.max_vertices = Some(max_vertices);
+        self
+    }
+
+    fn lookup(&self, material: &Option<String>, groups: &[String]) -> OcclusionMaterial {
+        if let Some(m) = material.as_ref().and_then(|m| self.materials.get(m)) {
+            return *m;
+        }
+        for group in groups.iter() {
+            if let Some(m) = self.materials.get(group) {
+                return *m;
+            }
+        }
+        self.default
+    }
+}
+
+fn parse_error(line: usize, message: &str) -> ::RStatus {
+    ::RStatus::Other(format!("line {}: {}", line, message))
+}
+
+fn parse_index(token: &str, num_vertices: usize, line: usize) -> Result<usize, ::RStatus> {
+    let index = match token.split('/').next().unwrap_or("").parse::<i64>() {
+        Ok(i) => i,
+        Err(_) => return Err(parse_error(line, &format!("invalid face index \"{}\"", token)))
+    };
+    let resolved = if index < 0 {
+        num_vertices as i64 + index
+    } else {
+        index - 1
+    };
+
+    if index == 0 || resolved < 0 || resolved >= num_vertices as i64 {
+        Err(parse_error(line, &format!("face index {} out of range", index)))
+    } else {
+        Ok(resolved as usize)
+    }
+}
+
+/// Reads the faces of an OBJ file as (material, polygon) pairs, splitting them as asked by the
+/// map.
+pub fn parse_obj<R: Read>(reader: R,
+                          map: &MaterialMap) -> Result<Vec<(OcclusionMaterial, Vec<Vector>)>,
+                                                       ::RStatus> {
+    let mut vertices = Vec::new();
+    let mut polygons = Vec::new();
+    let mut material = None;
+    let mut groups : Vec<String> = Vec::new();
+
+    for (pos, line) in BufReader::new(reader).lines().enumerate() {
+        let number = pos + 1;
+        let line = match line {
+            Ok(l) => l,
+            Err(e) => return Err(parse_error(number, &e.to_string()))
+        };
+        let line = line.split('#').next().unwrap_or("");
+        let mut tokens = line.split_whitespace();
+
+        match tokens.next() {
+            Some("v") => {
+                let coordinates : Vec<f32> = match tokens.take(3).map(|t| t.parse::<f32>())
+                                                         .collect() {
+                    Ok(c) => c,
+                    Err(_) => return Err(parse_error(number, "invalid vertex coordinate"))
+                };
+
+                if coordinates.len() != 3 {
+                    return Err(parse_error(number, "a vertex needs 3 coordinates"));
+                }
+                vertices.push(Vector { x: coordinates[0], y: coordinates[1], z: coordinates[2] });
+            }
+            Some("f") => {
+                let mut face = Vec::new();
+
+                for token in tokens {
+                    match parse_index(token, vertices.len(), number) {
+                        Ok(index) => face.push(vertices[index]),
+                        Err(e) => return Err(e)
+                    }
+                }
+                if face.len() < 3 {
+                    return Err(parse_error(number, "a face needs at least 3 vertices"));
+                }
+                let occlusion = map.lookup(&material, &groups);
+
+                if face.len() == 3 || (!map.triangulate && is_convex(&face)) {
+                    polygons.push((occlusion, face));
+                } else {
+                    for triangle in triangulate(&face) {
+                        polygons.push((occlusion, triangle));
+                    }
+                }
+            }
+            Some("g") | Some("o") => groups = tokens.map(|t| t.to_owned()).collect(),
+            Some("usemtl") => material = tokens.next().map(|t| t.to_owned()),
+            // normals, texture coordinates, smoothing groups, lines...
+            _ => {}
+        }
+    }
+    Ok(polygons)
+}
+
+/// Distance a vertex may be from the plane of its face, relative to the size of the face.
+const PLANARITY_TOLERANCE: f32 = 1e-3;
+
+/// Returns true if the polygon can be given to FMOD as it is: it must be flat, have an area and
+/// turn the same way at every vertex.
+fn is_convex(polygon: &[Vector]) -> bool {
+    let normal = vector::polygon_normal(polygon);
+    let length = vector::dot(&normal, &normal).sqrt();
+    let count = polygon.len();
+    let size = polygon.iter().map(|v| vector::sub(v, &polygon[0]))
+                      .map(|d| d.x.abs().max(d.y.abs()).max(d.z.abs()))
+                      .fold(0f32, f32::max);
+
+    // collinear or zero-area faces have no normal to check against
+    if length.is_nan() || length <= size * size * PLANARITY_TOLERANCE {
+        return false;
+    }
+    let normal = vector::scale(&normal, 1f32 / length);
+    let tolerance = size * PLANARITY_TOLERANCE;
+
+    polygon.iter().all(|v| vector::dot(&vector::sub(v, &polygon[0]), &normal).abs() <= tolerance)
+        && (0..count).all(|pos| {
+            let a = &polygon[pos];
+            let b = &polygon[(pos + 1) % count];
+            let c = &polygon[(pos + 2) % count];
+
+            vector::dot(&vector::cross(&vector::sub(b, a), &vector::sub(c, b)), &normal) >= 0f32
+        })
+}
+
+/// Projects the polygon on the plane of its main axis and splits it with ear clipping.
+fn triangulate(polygon: &[Vector]) -> Vec<Vec<Vector>> {
+    let normal = vector::polygon_normal(polygon);
+    let (ax, ay, az) = (normal.x.abs(), normal.y.abs(), normal.z.abs());
+    let points : Vec<(f32, f32)> = polygon.iter().map(|v| {
+        if az >= ax && az >= ay {
+            (v.x, v.y)
+        } else if ay >= ax {
+            (v.z, v.x)
+        } else {
+            (v.y, v.z)
+        }
+    }).collect();
+    let orientation = if (az >= ax && az >= ay && normal.z < 0f32)
+                         || (ay > az && ay >= ax && normal.y < 0f32)
+                         || (ax > az && ax > ay && normal.x < 0f32) {
+        -1f32
+    } else {
+        1f32
+    };
+    let cross = |a: (f32, f32), b: (f32, f32), c: (f32, f32)| {
+        ((b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0)) * orientation
+    };
+    let mut remaining : Vec<usize> = (0..polygon.len()).collect();
+    let mut triangles = Vec::with_capacity(polygon.len() - 2);
+
+    while remaining.len() > 3 {
+        let count = remaining.len();
+        let ear = (0..count).find(|pos| {
+            let (a, b, c) = (remaining[(pos + count - 1) % count], remaining[*pos],
+                             remaining[(pos + 1) % count]);
+
+            cross(points[a], points[b], points[c]) > 0f32 && remaining.iter().all(|&p| {
+                p == a || p == b || p == c || cross(points[a], points[b], points[p]) < 0f32
+                    || cross(points[b], points[c], points[p]) < 0f32
+                    || cross(points[c], points[a], points[p]) < 0f32
+            })
+        });
+        // degenerate polygons have no ear left, the rest is split as a fan
+        let pos = match ear {
+            Some(pos) => pos,
+            None => break
+        };
+
+        triangles.push(vec![polygon[remaining[(pos + count - 1) % count]], polygon[remaining[pos]],
+                            polygon[remaining[(pos + 1) % count]]]);
+        remaining.remove(pos);
+    }
+    for pos in 1..remaining.len() - 1 {
+        triangles.push(vec![polygon[remaining[0]], polygon[remaining[pos]],
+                            polygon[remaining[pos + 1]]]);
+    }
+    triangles
+}
+
+/// Creates a geometry just big enough for the polygons and adds them, failing if it needs more
+/// than the given limits.
+pub fn create_geometry(sys: &Sys, polygons: &[(OcclusionMaterial, Vec<Vector>)],
+                       max_polygons: Option<i32>,
+                       max_vertices: Option<i32>) -> Result<Geometry, ::RStatus> {
+    let (num_polygons, num_vertices) = geometry::get_size(polygons);
+
+    if max_polygons.map(|max| num_polygons > max) == Some(true) {
+        return Err(::RStatus::Other(format!("{} polygons needed, the limit is {}", num_polygons,
+                                            max_polygons.unwrap_or(0))));
+    }
+    if max_vertices.map(|max| num_vertices > max) == Some(true) {
+        return Err(::RStatus::Other(format!("{} vertices needed, the limit is {}", num_vertices,
+                                            max_vertices.unwrap_or(0))));
+    }
+    geometry::create_geometry(sys, polygons).map_err(::RStatus::FMOD)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(obj: &str, map: &MaterialMap) -> Result<Vec<(OcclusionMaterial, Vec<Vector>)>,
+                                                   ::RStatus> {
+        parse_obj(obj.as_bytes(), map)
+    }
+
+    fn coordinates(polygon: &[Vector]) -> Vec<(f32, f32, f32)> {
+        polygon.iter().map(|v| (v.x, v.y, v.z)).collect()
+    }
+
+    fn area(polygons: &[(OcclusionMaterial, Vec<Vector>)]) -> f32 {
+        polygons.iter().map(|p| {
+            let normal = vector::polygon_normal(&p.1);
+
+            vector::dot(&normal, &normal).sqrt() / 2f32
+        }).sum()
+    }
+
+    const SQUARE : &str = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n";
+
+    #[test]
+    fn parse_errors() {
+        let map = MaterialMap::new();
+        let error = |obj: &str| match parse(obj, &map) {
+            Err(::RStatus::Other(message)) => message,
+            other => panic!("unexpected result {:?}", other.map(|p| p.len())),
+        };
+
+        assert_eq!(error("v 0 0 0\nv 1 0\n"), "line 2: a vertex needs 3 coordinates");
+        assert_eq!(error("v 0 0 x\n"), "line 1: invalid vertex coordinate");
+        assert_eq!(error(&format!("{}# comment\nf 1 2\n", SQUARE)),
+                   "line 6: a face needs at least 3 vertices");
+        assert_eq!(error(&format!("{}f 1 2 5\n", SQUARE)), "line 5: face index 5 out of range");
+        assert_eq!(error(&format!("{}f 0 1 2\n", SQUARE)), "line 5: face index 0 out of range");
+        assert_eq!(error(&format!("{}f 1 2 -5\n", SQUARE)),
+                   "line 5: face index -5 out of range");
+        assert_eq!(error(&format!("{}f 1 a 3\n", SQUARE)), "line 5: invalid face index \"a\"");
+    }
+
+    #[test]
+    fn negative_indices() {
+        let map = MaterialMap::new();
+        let relative = parse(&format!("{}f -4/1 -3/2/1 -2//3\n", SQUARE), &map).unwrap();
+        let absolute = parse(&format!("{}f 1 2 3\n", SQUARE), &map).unwrap();
+
+        assert_eq!(relative.len(), 1);
+        assert_eq!(coordinates(&relative[0].1), coordinates(&absolute[0].1));
+
+        // relative to the vertices read so far, not to the whole file
+        let later = parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nf -1 -2 -3\nv 5 5 5\n", &map).unwrap();
+
+        assert_eq!(coordinates(&later[0].1), vec![(0f32, 1f32, 0f32), (1f32, 0f32, 0f32),
+                                                  (0f32, 0f32, 0f32)]);
+    }
+
+    #[test]
+    fn convex_faces() {
+        let map = MaterialMap::new();
+        let quad = parse(&format!("{}f 1 2 3 4\n", SQUARE), &map).unwrap();
+
+        assert_eq!(quad.len(), 1);
+        assert_eq!(quad[0].1.len(), 4);
+
+        let triangles = parse(&format!("{}f 1 2 3 4\n", SQUARE),
+                              &MaterialMap::new().triangulate(true)).unwrap();
+
+        assert_eq!(triangles.len(), 2);
+        assert!((area(&triangles) - 1f32).abs() < 1e-6);
+    }
+
+    #[test]
+    fn concave_faces() {
+        // an L shape, concave at (1, 1)
+        let obj = "v 0 0 0\nv 2 0 0\nv 2 1 0\nv 1 1 0\nv 1 2 0\nv 0 2 0\nf 1 2 3 4 5 6\n";
+        let polygons = parse(obj, &MaterialMap::new()).unwrap();
+
+        assert_eq!(polygons.len(), 4);
+        assert!(polygons.iter().all(|p| p.1.len() == 3));
+        assert!((area(&polygons) - 3f32).abs() < 1e-6);
+    }
+
+    #[test]
+    fn degenerate_faces() {
+        let map = MaterialMap::new();
+        // a quad bent out of its plane
+        let bent = parse("v 0 0 0\nv 1 0 0\nv 1 1 0.5\nv 0 1 0\nf 1 2 3 4\n", &map).unwrap();
+        // four collinear vertices
+        let flat = parse("v 0 0 0\nv 1 0 0\nv 2 0 0\nv 3 0 0\nf 1 2 3 4\n", &map).unwrap();
+        // almost flat, within the tolerance
+        let flat_enough = parse("v 0 0 0\nv 1 0 0\nv 1 1 0.0001\nv 0 1 0\nf 1 2 3 4\n",
+                                &map).unwrap();
+
+        assert!(bent.len() == 2 && bent.iter().all(|p| p.1.len() == 3));
+        assert!(flat.iter().all(|p| p.1.len() == 3));
+        assert_eq!(flat_enough.len(), 1);
+    }
+
+    #[test]
+    fn materials() {
+        let wood = OcclusionMaterial::new(0.5, 0.25, false);
+        let glass = OcclusionMaterial::new(0.1, 0.1, true);
+        let map = MaterialMap::new().material("wood", wood).material("window", glass);
+        let obj = format!("{}f 1 2 3\ng wall window\nf 1 2 3\nusemtl wood\nf 1 2 3\n\
+                           usemtl unknown\ng wall\nf 1 2 3\n", SQUARE);
+        let polygons = parse(&obj, &map).unwrap();
+        let materials : Vec<OcclusionMaterial> = polygons.iter().map(|p| p.0).collect();
+
+        assert_eq!(materials, vec![map.default, glass, wood, map.default]);
+    }
+}
@@ -0,0 +1,416 @@
+/*
+* Rust-FMOD - Copyright (c) 2018 Gomez Guillaume.
+*
+* The Original software, FmodEx library, is provided by FIRELIGHT TECHNOLOGIES.
+*
+* This software is provided 'as-is', without any express or implied warranty.
+* In no event will the authors be held liable for any damages arising from
+* the use of this software.
+*
+* Permission is granted to anyone to use this software for any purpose,
+* including commercial applications, and to alter it and redistribute it
+* freely, subject to the following restrictions:
+*
+* 1. The origin of this software must not be misrepresented; you must not claim
+*    that you wrote the original software. If you use this software in a product,
+*    an acknowledgment in the product documentation would be appreciated but is
+*    not required.
+*
+* 2. Altered source versions must be plainly marked as such, and must not be
+*    misrepresented as being the original software.
+*
+* 3. This notice may not be removed or altered from any source distribution.
+*/
+
+use ffi;
+use channel_group::ChannelGroup;
+use dsp::Dsp;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, PartialEq, Debug, Copy, Default)]
+struct GroupSettings {
+    volume: Option<f32>,
+    pitch: Option<f32>,
+    mute: Option<bool>,
+}
+
+/// A set of channel group settings and DSP parameter values describing a mix, such as
+/// "underwater" or "pause menu".
+///
+/// Only the settings which are set in the snapshot are changed when it is applied by a
+/// [`MixBlender`](struct.MixBlender.html).
+pub struct MixSnapshot {
+    groups: Vec<(ChannelGroup, GroupSettings)>,
+    parameters: Vec<(Dsp, i32, f32)>,
+}
+
+impl Clone for MixSnapshot {
+    fn clone(&self) -> MixSnapshot {
+        MixSnapshot {
+            groups: self.groups.iter().map(|g| (wrap_group(&g.0), g.1)).collect(),
+            parameters: self.parameters.iter().map(|p| (wrap_dsp(&p.0), p.1, p.2)).collect(),
+        }
+    }
+}
+
+impl PartialEq for MixSnapshot {
+    fn eq(&self, other: &MixSnapshot) -> bool {
+        self.groups.len() == other.groups.len()
+            && self.parameters.len() == other.parameters.len()
+            && self.groups.iter().zip(other.groups.iter())
+                   .all(|(a, b)| same_group(&a.0, &b.0) && a.1 == b.1)
+            && self.parameters.iter().zip(other.parameters.iter())
+                   .all(|(a, b)| same_dsp(&a.0, &b.0) && a.1 == b.1 && a.2 == b.2)
+    }
+}
+
+impl fmt::Debug for MixSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let groups : Vec<_> = self.groups.iter()
+                                  .map(|g| (ffi::FFI::unwrap(&g.0), g.1))
+                                  .collect();
+        let parameters : Vec<_> = self.parameters.iter()
+                                      .map(|p| (ffi::FFI::unwrap(&p.0), p.1, p.2))
+                                      .collect();
+
+        f.debug_struct("MixSnapshot")
+         .field("groups", &groups)
+         .field("parameters", &parameters)
+         .finish()
+    }
+}
+
+impl Default for MixSnapshot {
+    fn default() -> MixSnapshot {
+        MixSnapshot::new()
+    }
+}
+
+impl MixSnapshot {
+    pub fn new() -> MixSnapshot {
+        MixSnapshot {
+            groups: Vec::new(),
+            parameters: Vec::new(),
+        }
+    }
+
+    /// Creates a snapshot from the current volume, pitch and mute state of the given groups.
+    pub fn capture(groups: &[&ChannelGroup]) -> Result<MixSnapshot, ::Status> {
+        let mut snapshot = MixSnapshot::new();
+
+        for group in groups.iter() {
+            let settings = GroupSettings {
+                volume: Some(group.get_volume()?),
+                pitch: Some(group.get_pitch()?),
+                mute: Some(group.get_mute()?),
+            };
+
+            *snapshot.group_settings(group) = settings;
+        }
+        Ok(snapshot)
+    }
+
+    fn group_settings(&mut self, group: &ChannelGroup) -> &mut GroupSettings {
+        match self.groups.iter().position(|g| same_group(&g.0, group)) {
+            Some(pos) => &mut self.groups[pos].1,
+            None => {
+                self.groups.push((wrap_group(group), Default::default()));
+                &mut self.groups.last_mut().unwrap().1
+            }
+        }
+    }
+
+    pub fn set_volume(&mut self, group: &ChannelGroup, volume: f32) {
+        self.group_settings(group).volume = Some(volume);
+    }
+
+    pub fn set_pitch(&mut self, group: &ChannelGroup, pitch: f32) {
+        self.group_settings(group).pitch = Some(pitch);
+    }
+
+    pub fn set_mute(&mut self, group: &ChannelGroup, mute: bool) {
+        self.group_settings(group).mute = Some(mute);
+    }
+
+    pub fn set_parameter(&mut self, dsp: &Dsp, index: i32, value: f32) {
+        match self.parameters.iter().position(|p| same_dsp(&p.0, dsp) && p.1 == index) {
+            Some(pos) => self.parameters[pos].2 = value,
+            None => self.parameters.push((wrap_dsp(dsp), index, value))
+        }
+    }
+
+    /// Sets the cutoff frequency of a `LowPass` or `LowPassSimple` DSP, usually added to one of
+    /// the groups of the snapshot.
+    pub fn set_low_pass(&mut self, dsp: &Dsp, cutoff: f32) -> ::Status {
+        match dsp.get_type() {
+            Ok(::DspType::LowPass) | Ok(::DspType::LowPassSimple) => {
+                // the cutoff is the first parameter of both low pass filters
+                self.set_parameter(dsp, 0, cutoff);
+                ::Status::Ok
+            }
+            Ok(_) => ::Status::InvalidParam,
+            Err(e) => e
+        }
+    }
+}
+
+struct Layer {
+    id: usize,
+    snapshot: MixSnapshot,
+    weight: f32,
+    target: f32,
+    /// Weight change per second.
+    speed: f32,
+    removing: bool,
+}
+
+/// Blends the current mix with a stack of weighted [`MixSnapshot`](struct.MixSnapshot.html)s.
+///
+/// Snapshots are applied in the order they were pushed, each one being interpolated from the
+/// result of the previous ones by its weight. The values a group or DSP parameter had before it
+/// was first touched by a snapshot are restored once no snapshot uses it anymore.
+/// [`MixBlender::update`](struct.MixBlender.html#method.update) has to be called alongside
+/// [`Sys::update`](struct.Sys.html#method.update).
+///
+/// ```ignore
+/// let mut blender = rfmod::MixBlender::new();
+/// let underwater = blender.push(&underwater_snapshot, 1f32, Duration::from_millis(300)).unwrap();
+///
+/// // later
+/// blender.remove(underwater, Duration::from_millis(500));
+/// ```
+pub struct MixBlender {
+    groups: Vec<(ChannelGroup, f32, f32, bool)>,
+    parameters: Vec<(Dsp, i32, f32)>,
+    layers: Vec<Layer>,
+    next_id: usize,
+    last_update: Option<Instant>,
+}
+
+impl Default for MixBlender {
+    fn default() -> MixBlender {
+        MixBlender::new()
+    }
+}
+
+impl MixBlender {
+    pub fn new() -> MixBlender {
+        MixBlender {
+            groups: Vec::new(),
+            parameters: Vec::new(),
+            layers: Vec::new(),
+            next_id: 0,
+            last_update: None,
+        }
+    }
+
+    /// Pushes a snapshot on top of the stack. Its weight goes from 0 to `weight` over `fade`.
+    ///
+    /// Returns an identifier to use with [`set_weight`](#method.set_weight) and
+    /// [`remove`](#method.remove).
+    pub fn push(&mut self, snapshot: &MixSnapshot, weight: f32,
+                fade: Duration) -> Result<usize, ::Status> {
+        if !(0f32..=1f32).contains(&weight) {
+            return Err(::Status::InvalidParam);
+        }
+        for (group, _) in snapshot.groups.iter() {
+            if !self.groups.iter().any(|g| same_group(&g.0, group)) {
+                let volume = group.get_volume()?;
+                let pitch = group.get_pitch()?;
+                let mute = group.get_mute()?;
+
+                self.groups.push((wrap_group(group), volume, pitch, mute));
+            }
+        }
+        for &(ref dsp, index, _) in snapshot.parameters.iter() {
+            if !self.parameters.iter().any(|p| same_dsp(&p.0, dsp) && p.1 == index) {
+                let value = match dsp.get_parameter(index, 16) {
+                    Ok((v, _)) => v,
+                    Err(::RStatus::FMOD(e)) => return Err(e),
+                    Err(_) => return Err(::Status::InvalidParam)
+                };
+
+                self.parameters.push((wrap_dsp(dsp), index, value));
+            }
+        }
+
+        let id = self.next_id;
+
+        self.next_id += 1;
+        self.layers.push(Layer {
+            id: id,
+            snapshot: snapshot.clone(),
+            weight: 0f32,
+            target: weight,
+            speed: 0f32,
+            removing: false,
+        });
+        self.set_weight(id, weight, fade);
+        Ok(id)
+    }
+
+    /// Moves the weight of a snapshot to `weight` over `fade`.
+    pub fn set_weight(&mut self, id: usize, weight: f32, fade: Duration) -> ::Status {
+        if !(0f32..=1f32).contains(&weight) {
+            return ::Status::InvalidParam;
+        }
+        match self.layers.iter_mut().find(|l| l.id == id) {
+            Some(layer) => {
+                let seconds = duration_secs(fade);
+
+                layer.target = weight;
+                if seconds > 0f32 {
+                    layer.speed = (weight - layer.weight).abs() / seconds;
+                } else {
+                    layer.weight = weight;
+                    layer.speed = 0f32;
+                }
+                ::Status::Ok
+            }
+            None => ::Status::InvalidParam
+        }
+    }
+
+    pub fn get_weight(&self, id: usize) -> Option<f32> {
+        self.layers.iter().find(|l| l.id == id).map(|l| l.weight)
+    }
+
+    /// Fades the snapshot out over `fade` and removes it from the stack.
+    pub fn remove(&mut self, id: usize, fade: Duration) -> ::Status {
+        match self.set_weight(id, 0f32, fade) {
+            ::Status::Ok => {
+                for layer in self.layers.iter_mut().filter(|l| l.id == id) {
+                    layer.removing = true;
+                }
+                ::Status::Ok
+            }
+            e => e
+        }
+    }
+
+    /// Returns the identifiers of the snapshots in the stack, from bottom to top.
+    pub fn get_snapshots(&self) -> Vec<usize> {
+        self.layers.iter().map(|l| l.id).collect()
+    }
+
+    /// Removes every snapshot at once and restores the original values.
+    pub fn clear(&mut self) -> ::Status {
+        self.layers.clear();
+        self.apply()
+    }
+
+    /// Updates the blend with the time elapsed since the previous call.
+    pub fn update(&mut self) -> ::Status {
+        let now = Instant::now();
+        let elapsed = match self.last_update {
+            Some(last) => now.duration_since(last),
+            None => Duration::from_millis(0),
+        };
+
+        self.last_update = Some(now);
+        self.update_by(elapsed)
+    }
+
+    /// Updates the blend as if `elapsed` had passed since the previous update.
+    pub fn update_by(&mut self, elapsed: Duration) -> ::Status {
+        let seconds = duration_secs(elapsed);
+
+        for layer in self.layers.iter_mut() {
+            let step = layer.speed * seconds;
+
+            if layer.weight < layer.target {
+                layer.weight = (layer.weight + step).min(layer.target);
+            } else if layer.weight > layer.target {
+                layer.weight = (layer.weight - step).max(layer.target);
+            }
+        }
+        self.layers.retain(|l| !(l.removing && l.weight <= 0f32));
+        self.apply()
+    }
+
+    fn apply(&mut self) -> ::Status {
+        for &(ref group, volume, pitch, mute) in self.groups.iter() {
+            let (mut volume, mut pitch, mut mute) = (volume, pitch, mute);
+
+            for layer in self.layers.iter() {
+                if let Some(&(_, settings)) = layer.snapshot.groups.iter()
+                                                   .find(|g| same_group(&g.0, group)) {
+                    if let Some(v) = settings.volume {
+                        volume += (v - volume) * layer.weight;
+                    }
+                    if let Some(p) = settings.pitch {
+                        pitch += (p - pitch) * layer.weight;
+                    }
+                    if let Some(m) = settings.mute {
+                        if layer.weight >= 0.5f32 {
+                            mute = m;
+                        }
+                    }
+                }
+            }
+            match group.set_volume(volume) {
+                ::Status::Ok => {}
+                e => return e
+            }
+            match group.set_pitch(pitch) {
+                ::Status::Ok => {}
+                e => return e
+            }
+            match group.set_mute(mute) {
+                ::Status::Ok => {}
+                e => return e
+            }
+        }
+        for &(ref dsp, index, value) in self.parameters.iter() {
+            let mut value = value;
+
+            for layer in self.layers.iter() {
+                if let Some(p) = layer.snapshot.parameters.iter()
+                                      .find(|p| same_dsp(&p.0, dsp) && p.1 == index) {
+                    value += (p.2 - value) * layer.weight;
+                }
+            }
+
+            match dsp.set_parameter(index, value) {
+                ::Status::Ok => {}
+                e => return e
+            }
+        }
+
+        // forget what no snapshot uses anymore, its original value has just been restored
+        let layers = &self.layers;
+
+        self.groups.retain(|g| {
+            layers.iter().any(|l| l.snapshot.groups.iter().any(|s| same_group(&s.0, &g.0)))
+        });
+        self.parameters.retain(|p| {
+            layers.iter().any(|l| {
+                l.snapshot.parameters.iter().any(|s| same_dsp(&s.0, &p.0) && s.1 == p.1)
+            })
+        });
+        ::Status::Ok
+    }
+}
+
+// The snapshots and the blender don't own what they change, so they only keep non-owning
+// wrappers around them.
+fn wrap_group(group: &ChannelGroup) -> ChannelGroup {
+    ffi::FFI::wrap(ffi::FFI::unwrap(group))
+}
+
+fn wrap_dsp(dsp: &Dsp) -> Dsp {
+    ffi::FFI::wrap(ffi::FFI::unwrap(dsp))
+}
+
+fn same_group(a: &ChannelGroup, b: &ChannelGroup) -> bool {
+    ffi::FFI::unwrap(a) == ffi::FFI::unwrap(b)
+}
+
+fn same_dsp(a: &Dsp, b: &Dsp) -> bool {
+    ffi::FFI::unwrap(a) == ffi::FFI::unwrap(b)
+}
+
+fn duration_secs(d: Duration) -> f32 {
+    d.as_secs() as f32 + d.subsec_nanos() as f32 / 1_000_000_000f32
+}
@@ -0,0 +1,317 @@
+/*
+* Rust-FMOD - Copyright (c) 2018 Gomez Guillaume.
+*
+* The Original software, FmodEx library, is provided by FIRELIGHT TECHNOLOGIES.
+*
+* This software is provided 'as-is', without any express or implied warranty.
+* In no event will the authors be held liable for any damages arising from
+* the use of this software.
+*
+* Permission is granted to anyone to use this software for any purpose,
+* including commercial applications, and to alter it and redistribute it
+* freely, subject to the following restrictions:
+*
+* 1. The origin of this software must not be misrepresented; you must not claim
+*    that you wrote the original software. If you use this software in a product,
+*    an acknowledgment in the product documentation would be appreciated but is
+*    not required.
+*
+* 2. Altered source versions must be plainly marked as such, and must not be
+*    misrepresented as being the original software.
+*
+* 3. This notice may not be removed or altered from any source distribution.
+*/
+
+use channel_group::ChannelGroup;
+use dsp::Dsp;
+use fmod_sys::Sys;
+use std::collections::HashMap;
+use std::default::Default;
+
+/// Description of a built-in effect to attach to a bus.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct EffectDescription {
+    pub dsp_type  : ::DspType,
+    /// (index, value) pairs given to [`Dsp::set_parameter`](struct.Dsp.html#method.set_parameter).
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub parameters: Vec<(i32, f32)>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub bypass    : bool,
+}
+
+/// Nested description of a bus hierarchy, used to build a [`Mixer`](struct.Mixer.html).
+///
+/// With the `serde` feature, it can be read from any format supported by serde. In TOML:
+///
+/// ```toml
+/// name = "master"
+///
+/// [[children]]
+/// name = "music"
+/// volume = 0.8
+///
+/// [[children]]
+/// name = "sfx"
+///
+/// [[children.children]]
+/// name = "weapons"
+/// effects = [{ dsp_type = "Compressor" }]
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct BusDescription {
+    /// Name of the bus, used as path component. Must not contain '/'.
+    pub name    : String,
+    /// Default volume of the bus. 1.0 by default.
+    pub volume  : f32,
+    /// Default mute state of the bus.
+    pub mute    : bool,
+    /// Effects added to the bus, in signal order: the first one processes the sound first.
+    pub effects : Vec<EffectDescription>,
+    pub children: Vec<BusDescription>,
+}
+
+impl Default for BusDescription {
+    fn default() -> BusDescription {
+        BusDescription {
+            name: String::new(),
+            volume: 1f32,
+            mute: false,
+            effects: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+}
+
+impl BusDescription {
+    pub fn new(name: &str) -> BusDescription {
+        BusDescription {
+            name: name.to_owned(),
+            .. Default::default()
+        }
+    }
+
+    pub fn volume(mut self, volume: f32) -> BusDescription {
+        self.volume = volume;
+        self
+    }
+
+    pub fn mute(mut self, mute: bool) -> BusDescription {
+        self.mute = mute;
+        self
+    }
+
+    pub fn effect(mut self, effect: EffectDescription) -> BusDescription {
+        self.effects.push(effect);
+        self
+    }
+
+    pub fn child(mut self, child: BusDescription) -> BusDescription {
+        self.children.push(child);
+        self
+    }
+}
+
+struct Bus {
+    group: ChannelGroup,
+    effects: Vec<Dsp>,
+    volume: f32,
+    mute: bool,
+}
+
+/// A tree of [`ChannelGroup`](struct.ChannelGroup.html)s built from a
+/// [`BusDescription`](struct.BusDescription.html).
+///
+/// The root of the description is the master channel group of the system; the other buses are
+/// created by the mixer and released when it is dropped. Buses are looked up by their path from
+/// the root, such as `"sfx/weapons"`. The root itself has the empty path.
+pub struct Mixer {
+    buses: Vec<Bus>,
+    paths: HashMap<String, usize>,
+}
+
+impl Mixer {
+    pub fn new(sys: &Sys, description: &BusDescription) -> Result<Mixer, ::Status> {
+        let master = sys.get_master_channel_group()?;
+        let mut mixer = Mixer {
+            buses: Vec::new(),
+            paths: HashMap::new(),
+        };
+
+        match mixer.add_bus(sys, master, String::new(), description) {
+            ::Status::Ok => Ok(mixer),
+            e => Err(e)
+        }
+    }
+
+    fn add_bus(&mut self, sys: &Sys, group: ChannelGroup, path: String,
+               description: &BusDescription) -> ::Status {
+        if self.paths.contains_key(&path) {
+            return ::Status::InvalidParam;
+        }
+        let mut effects = Vec::with_capacity(description.effects.len());
+
+        for effect in description.effects.iter() {
+            let dsp = match sys.create_DSP_by_type(effect.dsp_type) {
+                Ok(d) => d,
+                Err(e) => return e
+            };
+
+            for &(index, value) in effect.parameters.iter() {
+                match dsp.set_parameter(index, value) {
+                    ::Status::Ok => {}
+                    e => return e
+                }
+            }
+            match dsp.set_bypass(effect.bypass) {
+                ::Status::Ok => {}
+                e => return e
+            }
+            effects.push(dsp);
+        }
+        // add_DSP inserts at the head of the group, so the last one added processes the sound
+        // first
+        for dsp in effects.iter().rev() {
+            if let Err(e) = group.add_DSP(dsp) {
+                return e;
+            }
+        }
+
+        let bus = Bus {
+            group: group,
+            effects: effects,
+            volume: description.volume,
+            mute: description.mute,
+        };
+
+        match apply_defaults(&bus) {
+            ::Status::Ok => {}
+            e => return e
+        }
+        self.paths.insert(path.clone(), self.buses.len());
+        self.buses.push(bus);
+
+        let parent = self.buses.len() - 1;
+
+        for child in description.children.iter() {
+            if child.name.is_empty() || child.name.contains('/') {
+                return ::Status::InvalidParam;
+            }
+            let child_path = if path.is_empty() {
+                child.name.clone()
+            } else {
+                format!("{}/{}", path, child.name)
+            };
+            let group = match sys.create_channel_group(&child.name) {
+                Ok(g) => g,
+                Err(e) => return e
+            };
+
+            match self.buses[parent].group.add_group(&group) {
+                ::Status::Ok => {}
+                e => return e
+            }
+            match self.add_bus(sys, group, child_path, child) {
+                ::Status::Ok => {}
+                e => return e
+            }
+        }
+        ::Status::Ok
+    }
+
+    fn get_bus(&self, path: &str) -> Option<&Bus> {
+        self.paths.get(path.trim_matches('/')).map(|index| &self.buses[*index])
+    }
+
+    fn get_bus_mut(&mut self, path: &str) -> Option<&mut Bus> {
+        match self.paths.get(path.trim_matches('/')) {
+            Some(index) => Some(&mut self.buses[*index]),
+            None => None
+        }
+    }
+
+    /// Returns the channel group of the bus at the given path, such as `"sfx/weapons"`.
+    pub fn get_group(&self, path: &str) -> Option<&ChannelGroup> {
+        self.get_bus(path).map(|bus| &bus.group)
+    }
+
+    /// Returns the effects of the bus at the given path, in signal order.
+    pub fn get_effects(&self, path: &str) -> Option<&[Dsp]> {
+        self.get_bus(path).map(|bus| &bus.effects[..])
+    }
+
+    /// Adds an effect at the head of the bus, where it processes the sound before the other
+    /// effects. The mixer keeps it alive.
+    pub fn add_effect(&mut self, path: &str, dsp: Dsp) -> ::Status {
+        match self.get_bus_mut(path) {
+            Some(bus) => match bus.group.add_DSP(&dsp) {
+                Ok(_) => {
+                    bus.effects.insert(0, dsp);
+                    ::Status::Ok
+                }
+                Err(e) => e
+            },
+            None => ::Status::InvalidParam
+        }
+    }
+
+    /// Returns the paths of every bus, parents before their children.
+    pub fn get_paths(&self) -> Vec<&str> {
+        let mut paths = vec![""; self.buses.len()];
+
+        for (path, index) in self.paths.iter() {
+            paths[*index] = path;
+        }
+        paths
+    }
+
+    /// Returns (volume, mute).
+    pub fn get_defaults(&self, path: &str) -> Option<(f32, bool)> {
+        self.get_bus(path).map(|bus| (bus.volume, bus.mute))
+    }
+
+    /// Changes the defaults of a bus. They are applied to the channel group right away.
+    pub fn set_defaults(&mut self, path: &str, volume: f32, mute: bool) -> ::Status {
+        match self.get_bus_mut(path) {
+            Some(bus) => {
+                bus.volume = volume;
+                bus.mute = mute;
+                apply_defaults(bus)
+            }
+            None => ::Status::InvalidParam
+        }
+    }
+
+    /// Restores the default volume and mute state of every bus.
+    pub fn reset(&self) -> ::Status {
+        for bus in self.buses.iter() {
+            match apply_defaults(bus) {
+                ::Status::Ok => {}
+                e => return e
+            }
+        }
+        ::Status::Ok
+    }
+}
+
+impl Drop for Mixer {
+    fn drop(&mut self) {
+        // children have to go before their parent
+        while let Some(mut bus) = self.buses.pop() {
+            for dsp in bus.effects.iter() {
+                dsp.remove();
+            }
+            bus.effects.clear();
+            bus.group.release();
+        }
+    }
+}
+
+fn apply_defaults(bus: &Bus) -> ::Status {
+    match bus.group.set_volume(bus.volume) {
+        ::Status::Ok => bus.group.set_mute(bus.mute),
+        e => e
+    }
+}
@@ -0,0 +1,460 @@
+/*
+* Rust-FMOD - Copyright (c) 2018 Gomez Guillaume.
+*
+* The Original software, FmodEx library, is provided by FIRELIGHT TECHNOLOGIES.
+*
+* This software is provided 'as-is', without any express or implied warranty.
+* In no event will the authors be held liable for any damages arising from
+* the use of this software.
+*
+* Permission is granted to anyone to use this software for any purpose,
+* including commercial applications, and to alter it and redistribute it
+* freely, subject to the following restrictions:
+*
+* 1. The origin of this software must not be misrepresented; you must not claim
+*    that you wrote the original software. If you use this software in a product,
+*    an acknowledgment in the product documentation would be appreciated but is
+*    not required.
+*
+* 2. Altered source versions must be plainly marked as such, and must not be
+*    misrepresented as being the original software.
+*
+* 3. This notice may not be removed or altered from any source distribution.
+*/
+
+use vector;
+use vector::Vector;
+use geometry::{Geometry, OcclusionMaterial};
+use geometry_builder::GeometryTransform;
+use fmod_sys::Sys;
+use utils::json_float;
+use std::fmt::Write;
+
+/// A polygon of an [`OcclusionMirror`](struct.OcclusionMirror.html), in world space.
+#[derive(Debug, PartialEq, Clone)]
+pub struct MirrorPolygon {
+    /// Id returned when the geometry was added to the mirror.
+    pub geometry: usize,
+    /// Index of the polygon in its geometry.
+    pub index   : i32,
+    pub vertices: Vec<Vector>,
+    pub material: OcclusionMaterial,
+}
+
+/// A polygon crossed by an occlusion query.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct OcclusionHit {
+    pub geometry: usize,
+    pub polygon : i32,
+    pub point   : Vector,
+    /// Position of the hit between the listener (0.0) and the source (1.0).
+    pub distance: f32,
+}
+
+/// Result of
+/// [`OcclusionMirror::occlusion_between`](struct.OcclusionMirror.html#method.occlusion_between).
+#[derive(Debug, PartialEq, Clone)]
+pub struct OcclusionQuery {
+    pub listener: Vector,
+    pub source  : Vector,
+    pub direct  : f32,
+    pub reverb  : f32,
+    /// Hits sorted from the listener to the source.
+    pub hits    : Vec<OcclusionHit>,
+}
+
+struct MirrorGeometry {
+    active: bool,
+}
+
+/// A copy of the polygons of one or several [`Geometry`](struct.Geometry.html) objects, used to
+/// understand why a sound is occluded.
+///
+/// Occlusion is computed like FMOD does: every polygon crossed by the segment between the
+/// listener and the source lets `1 - occlusion` of the sound through. A single sided polygon only
+/// occludes when the segment enters it from the side its normal points to, the normal following
+/// the right-hand rule over the vertex order.
+///
+/// The mirror is a snapshot: it has to be refreshed with
+/// [`update_geometry`](#method.update_geometry) when a geometry is moved or edited.
+///
+/// ```ignore
+/// let mut mirror = OcclusionMirror::new();
+/// let id = mirror.add_geometry(&geometry).unwrap();
+/// let query = mirror.occlusion_between(&listener, &source);
+///
+/// println!("{:?} vs {:?}", (query.direct, query.reverb),
+///          sys.get_geometry_occlusion(&listener, &source));
+/// File::create("occlusion.obj").unwrap().write_all(mirror.to_obj(&[query]).as_bytes());
+/// ```
+pub struct OcclusionMirror {
+    geometries: Vec<MirrorGeometry>,
+    polygons: Vec<MirrorPolygon>,
+}
+
+impl Default for OcclusionMirror {
+    fn default() -> OcclusionMirror {
+        OcclusionMirror::new()
+    }
+}
+
+type Polygons = Vec<(OcclusionMaterial, Vec<Vector>)>;
+
+/// Returns the active state and the polygons of the geometry, in world space.
+fn read_polygons(geometry: &Geometry) -> Result<(bool, Polygons), ::Status> {
+    let active = geometry.get_active()?;
+    let transform = match (geometry.get_position(), geometry.get_rotation(),
+                           geometry.get_scale()) {
+        (Ok(position), Ok((forward, up)), Ok(scale)) => GeometryTransform {
+            position: position,
+            forward: forward,
+            up: up,
+            scale: scale,
+        },
+        (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => return Err(e)
+    };
+    let mut polygons = Vec::new();
+
+    for polygon in geometry.polygons() {
+        match polygon {
+            Ok(p) => polygons.push((p.material,
+                                    p.vertices.iter().map(|v| transform.apply(v)).collect())),
+            Err(e) => return Err(e)
+        }
+    }
+    Ok((active, polygons))
+}
+
+/// Returns the position of the intersection between the segment and the polygon, if any.
+fn intersect(polygon: &MirrorPolygon, from: &Vector, to: &Vector) -> Option<f32> {
+    if polygon.vertices.len() < 3 {
+        return None;
+    }
+    let normal = vector::polygon_normal(&polygon.vertices);
+    let direction = vector::sub(to, from);
+    let denominator = vector::dot(&normal, &direction);
+
+    // parallel, or going out of the back of a single sided polygon
+    if denominator == 0f32 || (!polygon.material.double_sided && denominator > 0f32) {
+        return None;
+    }
+    let t = vector::dot(&normal, &vector::sub(&polygon.vertices[0], from)) / denominator;
+
+    if !(0f32..=1f32).contains(&t) {
+        return None;
+    }
+    let point = vector::add(from, &vector::scale(&direction, t));
+    let (ax, ay, az) = (normal.x.abs(), normal.y.abs(), normal.z.abs());
+    let project = |v: &Vector| if az >= ax && az >= ay {
+        (v.x, v.y)
+    } else if ay >= ax {
+        (v.z, v.x)
+    } else {
+        (v.y, v.z)
+    };
+    let (px, py) = project(&point);
+    let mut inside = false;
+    let count = polygon.vertices.len();
+
+    // even-odd rule, which also handles concave polygons
+    for pos in 0..count {
+        let (x1, y1) = project(&polygon.vertices[pos]);
+        let (x2, y2) = project(&polygon.vertices[(pos + 1) % count]);
+
+        if (y1 > py) != (y2 > py) && px < x1 + (py - y1) * (x2 - x1) / (y2 - y1) {
+            inside = !inside;
+        }
+    }
+    if inside {
+        Some(t)
+    } else {
+        None
+    }
+}
+
+impl OcclusionMirror {
+    pub fn new() -> OcclusionMirror {
+        OcclusionMirror {
+            geometries: Vec::new(),
+            polygons: Vec::new(),
+        }
+    }
+
+    /// Copies the polygons of a geometry, with its position, rotation and scale applied, and
+    /// returns the id of the copy. Inactive geometries are kept but don't occlude.
+    pub fn add_geometry(&mut self, geometry: &Geometry) -> Result<usize, ::Status> {
+        match read_polygons(geometry) {
+            Ok((active, polygons)) => {
+                let id = self.add_polygons(&polygons, &Default::default());
+
+                self.geometries[id].active = active;
+                Ok(id)
+            }
+            Err(e) => Err(e)
+        }
+    }
+
+    /// Adds polygons which are not in FMOD, such as the ones of a
+    /// [`GeometryBuilder`](struct.GeometryBuilder.html), and returns their geometry id.
+    pub fn add_polygons(&mut self, polygons: &[(OcclusionMaterial, Vec<Vector>)],
+                        transform: &GeometryTransform) -> usize {
+        let id = self.geometries.len();
+
+        self.geometries.push(MirrorGeometry { active: true });
+        for (index, &(material, ref vertices)) in polygons.iter().enumerate() {
+            self.polygons.push(MirrorPolygon {
+                geometry: id,
+                index: index as i32,
+                vertices: vertices.iter().map(|v| transform.apply(v)).collect(),
+                material: material,
+            });
+        }
+        id
+    }
+
+    /// Reads again the polygons of a geometry added with
+    /// [`add_geometry`](#method.add_geometry).
+    pub fn update_geometry(&mut self, id: usize, geometry: &Geometry) -> ::Status {
+        if id >= self.geometries.len() {
+            return ::Status::InvalidParam;
+        }
+        let (active, polygons) = match read_polygons(geometry) {
+            Ok(p) => p,
+            Err(e) => return e
+        };
+
+        self.polygons.retain(|p| p.geometry != id);
+        self.geometries[id].active = active;
+        for (index, (material, vertices)) in polygons.into_iter().enumerate() {
+            self.polygons.push(MirrorPolygon {
+                geometry: id,
+                index: index as i32,
+                vertices: vertices,
+                material: material,
+            });
+        }
+        ::Status::Ok
+    }
+
+    pub fn set_active(&mut self, id: usize, active: bool) -> ::Status {
+        match self.geometries.get_mut(id) {
+            Some(geometry) => {
+                geometry.active = active;
+                ::Status::Ok
+            }
+            None => ::Status::InvalidParam
+        }
+    }
+
+    pub fn get_polygons(&self) -> &[MirrorPolygon] {
+        &self.polygons
+    }
+
+    /// Computes the occlusion between the listener and the source and reports the polygons
+    /// crossed.
+    pub fn occlusion_between(&self, listener: &Vector, source: &Vector) -> OcclusionQuery {
+        let mut hits = Vec::new();
+        let mut direct = 1f32;
+        let mut reverb = 1f32;
+
+        for polygon in self.polygons.iter().filter(|p| self.geometries[p.geometry].active) {
+            if let Some(t) = intersect(polygon, listener, source) {
+                direct *= 1f32 - polygon.material.direct_occlusion;
+                reverb *= 1f32 - polygon.material.reverb_occlusion;
+                hits.push(OcclusionHit {
+                    geometry: polygon.geometry,
+                    polygon: polygon.index,
+                    point: vector::add(listener, &vector::scale(&vector::sub(source, listener),
+                                                                 t)),
+                    distance: t,
+                });
+            }
+        }
+        hits.sort_by(|a, b| a.distance.partial_cmp(&b.distance)
+                                      .unwrap_or(::std::cmp::Ordering::Equal));
+        OcclusionQuery {
+            listener: *listener,
+            source: *source,
+            direct: 1f32 - direct,
+            reverb: 1f32 - reverb,
+            hits: hits,
+        }
+    }
+
+    /// Runs the same query with the mirror and with
+    /// [`Sys::get_geometry_occlusion`](struct.Sys.html#method.get_geometry_occlusion).
+    ///
+    /// Returns:
+    ///
+    /// Ok(mirror query, (fmod direct, fmod reverb))
+    pub fn compare(&self, sys: &Sys, listener: &Vector,
+                   source: &Vector) -> Result<(OcclusionQuery, (f32, f32)), ::Status> {
+        match sys.get_geometry_occlusion(listener, source) {
+            Ok(fmod) => Ok((self.occlusion_between(listener, source), fmod)),
+            Err(e) => Err(e)
+        }
+    }
+
+    /// Exports the polygons and the queries in the Wavefront OBJ format. Each geometry is an
+    /// object, polygons hit by a query are put in the `hit` group and each query is a line from
+    /// the listener to the source.
+    pub fn to_obj(&self, queries: &[OcclusionQuery]) -> String {
+        let mut out = String::from("# rfmod occlusion mirror\n");
+        let mut next_vertex = 1;
+        let is_hit = |polygon: &MirrorPolygon| queries.iter().any(|q| q.hits.iter().any(|h| {
+            h.geometry == polygon.geometry && h.polygon == polygon.index
+        }));
+
+        for (id, geometry) in self.geometries.iter().enumerate() {
+            let suffix = if geometry.active { "" } else { "_inactive" };
+
+            let _ = writeln!(out, "o geometry_{}{}", id, suffix);
+            for polygon in self.polygons.iter().filter(|p| p.geometry == id) {
+                for v in polygon.vertices.iter() {
+                    let _ = writeln!(out, "v {} {} {}", v.x, v.y, v.z);
+                }
+                let _ = writeln!(out, "g {}", if is_hit(polygon) { "hit" } else { "polygons" });
+                let indices : Vec<String> = (next_vertex..next_vertex + polygon.vertices.len())
+                                                .map(|i| i.to_string()).collect();
+
+                let _ = writeln!(out, "f {}", indices.join(" "));
+                next_vertex += polygon.vertices.len();
+            }
+        }
+        for (pos, query) in queries.iter().enumerate() {
+            let _ = writeln!(out, "o query_{}", pos);
+            let _ = writeln!(out, "v {} {} {}", query.listener.x, query.listener.y,
+                             query.listener.z);
+            let _ = writeln!(out, "v {} {} {}", query.source.x, query.source.y, query.source.z);
+            let _ = writeln!(out, "l {} {}", next_vertex, next_vertex + 1);
+            next_vertex += 2;
+        }
+        out
+    }
+
+    /// Exports the polygons and the queries as JSON.
+    pub fn to_json(&self, queries: &[OcclusionQuery]) -> String {
+        let vector = |v: &Vector| format!("[{},{},{}]", json_float(v.x), json_float(v.y),
+                                          json_float(v.z));
+        let mut out = String::from("{\"geometries\":[");
+
+        for (id, geometry) in self.geometries.iter().enumerate() {
+            if id > 0 {
+                out.push(',');
+            }
+            let _ = write!(out, "{{\"id\":{},\"active\":{},\"polygons\":[", id, geometry.active);
+            for (pos, polygon) in self.polygons.iter().filter(|p| p.geometry == id).enumerate() {
+                let vertices : Vec<String> = polygon.vertices.iter().map(&vector).collect();
+
+                if pos > 0 {
+                    out.push(',');
+                }
+                let _ = write!(out, "{{\"index\":{},\"direct_occlusion\":{},\
+                                     \"reverb_occlusion\":{},\"double_sided\":{},\
+                                     \"vertices\":[{}]}}",
+                               polygon.index, json_float(polygon.material.direct_occlusion),
+                               json_float(polygon.material.reverb_occlusion),
+                               polygon.material.double_sided, vertices.join(","));
+            }
+            out.push_str("]}");
+        }
+        out.push_str("],\"queries\":[");
+        for (pos, query) in queries.iter().enumerate() {
+            let hits : Vec<String> = query.hits.iter().map(|h| {
+                format!("{{\"geometry\":{},\"polygon\":{},\"point\":{},\"distance\":{}}}",
+                        h.geometry, h.polygon, vector(&h.point), json_float(h.distance))
+            }).collect();
+
+            if pos > 0 {
+                out.push(',');
+            }
+            let _ = write!(out, "{{\"listener\":{},\"source\":{},\"direct\":{},\"reverb\":{},\
+                                 \"hits\":[{}]}}",
+                           vector(&query.listener), vector(&query.source),
+                           json_float(query.direct), json_float(query.reverb), hits.join(","));
+        }
+        out.push_str("]}");
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(x: f32, y: f32, z: f32) -> Vector {
+        Vector { x: x, y: y, z: z }
+    }
+
+    /// A 2x2 square on the z = `z` plane, its normal pointing to +z.
+    fn square(z: f32) -> Vec<Vector> {
+        vec![point(-1f32, -1f32, z), point(1f32, -1f32, z), point(1f32, 1f32, z),
+             point(-1f32, 1f32, z)]
+    }
+
+    fn mirror(polygons: &[(OcclusionMaterial, Vec<Vector>)]) -> OcclusionMirror {
+        let mut mirror = OcclusionMirror::new();
+
+        mirror.add_polygons(polygons, &Default::default());
+        mirror
+    }
+
+    #[test]
+    fn single_sided_hits() {
+        let mirror = mirror(&[(OcclusionMaterial::new(0.5, 0.25, false), square(0f32))]);
+        let front = mirror.occlusion_between(&point(0f32, 0f32, 2f32), &point(0f32, 0f32, -2f32));
+        let back = mirror.occlusion_between(&point(0f32, 0f32, -2f32), &point(0f32, 0f32, 2f32));
+
+        assert_eq!(front.direct, 0.5);
+        assert_eq!(front.reverb, 0.25);
+        assert_eq!(front.hits.len(), 1);
+        assert_eq!(front.hits[0].distance, 0.5);
+        assert_eq!(front.hits[0].point, point(0f32, 0f32, 0f32));
+        assert_eq!((back.direct, back.reverb), (0f32, 0f32));
+        assert!(back.hits.is_empty());
+    }
+
+    #[test]
+    fn double_sided_hits() {
+        let mirror = mirror(&[(OcclusionMaterial::new(0.5, 0.25, true), square(0f32))]);
+        let front = mirror.occlusion_between(&point(0f32, 0f32, 2f32), &point(0f32, 0f32, -2f32));
+        let back = mirror.occlusion_between(&point(0f32, 0f32, -2f32), &point(0f32, 0f32, 2f32));
+
+        assert_eq!((front.direct, front.reverb), (0.5, 0.25));
+        assert_eq!((back.direct, back.reverb), (0.5, 0.25));
+        assert_eq!(back.hits.len(), 1);
+    }
+
+    #[test]
+    fn misses() {
+        let mirror = mirror(&[(OcclusionMaterial::new(1f32, 1f32, true), square(0f32))]);
+        let queries = [
+            // beside the polygon
+            (point(2f32, 0f32, 1f32), point(2f32, 0f32, -1f32)),
+            // stops before it
+            (point(0f32, 0f32, 2f32), point(0f32, 0f32, 1f32)),
+            // along its plane
+            (point(-2f32, 0f32, 0f32), point(2f32, 0f32, 0f32)),
+        ];
+
+        for (listener, source) in queries.iter() {
+            assert!(mirror.occlusion_between(listener, source).hits.is_empty());
+        }
+    }
+
+    #[test]
+    fn several_hits() {
+        let mut mirror = mirror(&[(OcclusionMaterial::new(0.5, 0.5, true), square(-1f32)),
+                                  (OcclusionMaterial::new(0.5, 0f32, true), square(1f32))]);
+        let query = mirror.occlusion_between(&point(0f32, 0f32, -2f32), &point(0f32, 0f32, 2f32));
+
+        // each polygon lets half of the sound through
+        assert_eq!((query.direct, query.reverb), (0.75, 0.5));
+        assert_eq!(query.hits.iter().map(|h| h.polygon).collect::<Vec<i32>>(), vec![0, 1]);
+        assert_eq!(query.hits[0].distance, 0.25);
+
+        mirror.set_active(0, false);
+        assert!(mirror.occlusion_between(&point(0f32, 0f32, -2f32),
+                                         &point(0f32, 0f32, 2f32)).hits.is_empty());
+    }
+}
@@ -0,0 +1,244 @@
+/*
+* Rust-FMOD - Copyright (c) 2018 Gomez Guillaume.
+*
+* The Original software, FmodEx library, is provided by FIRELIGHT TECHNOLOGIES.
+*
+* This software is provided 'as-is', without any express or implied warranty.
+* In no event will the authors be held liable for any damages arising from
+* the use of this software.
+*
+* Permission is granted to anyone to use this software for any purpose,
+* including commercial applications, and to alter it and redistribute it
+* freely, subject to the following restrictions:
+*
+* 1. The origin of this software must not be misrepresented; you must not claim
+*    that you wrote the original software. If you use this software in a product,
+*    an acknowledgment in the product documentation would be appreciated but is
+*    not required.
+*
+* 2. Altered source versions must be plainly marked as such, and must not be
+*    misrepresented as being the original software.
+*
+* 3. This notice may not be removed or altered from any source distribution.
+*/
+
+use ffi;
+use dsp::Dsp;
+use dsp_connection::DspConnection;
+use channel_group::ChannelGroup;
+use fmod_sys::Sys;
+
+/// A shared effect chain fed by sends, such as a reverb used by several channel groups.
+///
+/// The bus mixes every send into an input unit, runs the result through its effects in order and
+/// adds it to the DSP head of the system or of a channel group, in parallel of the dry signal.
+/// Sends are taken after the volume of their channel or group and are created with
+/// [`ChannelGroup::create_send`](struct.ChannelGroup.html#method.create_send) or
+/// [`Channel::set_send_level`](struct.Channel.html#method.set_send_level); their level is the mix
+/// of their [`DspConnection`](struct.DspConnection.html).
+///
+/// ```ignore
+/// let mut reverb = ReturnBus::new(&sys).unwrap();
+///
+/// reverb.add_effect(sys.create_DSP_by_type(rfmod::DspType::SFXReverb).unwrap());
+/// music.create_send(&reverb, 0.2);
+/// sfx.create_send(&reverb, 0.6);
+/// ```
+///
+/// The bus keeps its effects alive. When it is dropped, every send is disconnected.
+pub struct ReturnBus {
+    sys: Sys,
+    output: Dsp,
+    input: Dsp,
+    effects: Vec<Dsp>,
+    level: f32,
+}
+
+impl ReturnBus {
+    /// Creates a bus returning into the system DSP head.
+    pub fn new(sys: &Sys) -> Result<ReturnBus, ::Status> {
+        match sys.get_DSP_head() {
+            Ok(head) => ReturnBus::with_output(sys, head),
+            Err(e) => Err(e)
+        }
+    }
+
+    /// Creates a bus returning into the DSP head of a channel group. The group must not send to
+    /// the bus, directly or through its children.
+    pub fn new_in_group(sys: &Sys, group: &ChannelGroup) -> Result<ReturnBus, ::Status> {
+        match group.get_DSP_head() {
+            Ok(head) => ReturnBus::with_output(sys, head),
+            Err(e) => Err(e)
+        }
+    }
+
+    fn with_output(sys: &Sys, output: Dsp) -> Result<ReturnBus, ::Status> {
+        let input = sys.create_DSP_by_type(::DspType::Mixer)?;
+
+        output.add_input(ffi::FFI::wrap(ffi::FFI::unwrap(&input)))?;
+        Ok(ReturnBus {
+            sys: ffi::FFI::wrap(ffi::FFI::unwrap(sys)),
+            output: output,
+            input: input,
+            effects: Vec::new(),
+            level: 1f32,
+        })
+    }
+
+    /// Returns the unit mixing the sends, the first one of the bus.
+    pub fn get_input(&self) -> &Dsp {
+        &self.input
+    }
+
+    pub fn get_effects(&self) -> &[Dsp] {
+        &self.effects
+    }
+
+    fn get_last(&self) -> &Dsp {
+        self.effects.last().unwrap_or(&self.input)
+    }
+
+    /// Returns the connection of `input` to `dsp`, if any.
+    fn find_connection(dsp: &Dsp, input: &Dsp) -> Result<Option<DspConnection>, ::Status> {
+        let target = ffi::FFI::unwrap(input);
+
+        for index in 0..dsp.get_num_inputs().unwrap_or(0) {
+            match dsp.get_input(index) {
+                Ok((d, connection)) => if ffi::FFI::unwrap(&d) == target {
+                    return Ok(Some(connection));
+                },
+                Err(e) => return Err(e)
+            }
+        }
+        Ok(None)
+    }
+
+    /// Appends an effect at the end of the bus, the closest to the output.
+    pub fn add_effect(&mut self, dsp: Dsp) -> ::Status {
+        match self.sys.lock_DSP() {
+            ::Status::Ok => {}
+            e => return e
+        }
+        let status = self.insert_last(&dsp);
+
+        if status == ::Status::Ok {
+            self.effects.push(dsp);
+        }
+        match self.sys.unlock_DSP() {
+            ::Status::Ok => status,
+            e => e
+        }
+    }
+
+    fn insert_last(&self, dsp: &Dsp) -> ::Status {
+        let last = self.get_last();
+
+        match self.output.disconnect_from(ffi::FFI::wrap(ffi::FFI::unwrap(last))) {
+            ::Status::Ok => {}
+            e => return e
+        }
+        let status = match dsp.add_input(ffi::FFI::wrap(ffi::FFI::unwrap(last))) {
+            Ok(_) => match self.output.add_input(ffi::FFI::wrap(ffi::FFI::unwrap(dsp))) {
+                Ok(connection) => connection.set_mix(self.level),
+                Err(e) => e
+            },
+            Err(e) => e
+        };
+
+        if status != ::Status::Ok {
+            // put the bus back the way it was
+            dsp.disconnect_all(true, true);
+            if let Ok(connection) = self.output.add_input(ffi::FFI::wrap(ffi::FFI::unwrap(last))) {
+                connection.set_mix(self.level);
+            }
+        }
+        status
+    }
+
+    /// Sets the level at which the bus is added to its output.
+    pub fn set_return_level(&mut self, level: f32) -> ::Status {
+        match self.sys.lock_DSP() {
+            ::Status::Ok => {}
+            e => return e
+        }
+        let status = match ReturnBus::find_connection(&self.output, self.get_last()) {
+            Ok(Some(connection)) => connection.set_mix(level),
+            Ok(None) => ::Status::DSPNotFound,
+            Err(e) => e
+        };
+
+        if status == ::Status::Ok {
+            self.level = level;
+        }
+        match self.sys.unlock_DSP() {
+            ::Status::Ok => status,
+            e => e
+        }
+    }
+
+    pub fn get_return_level(&self) -> f32 {
+        self.level
+    }
+
+    /// Sends the output of `source` to the bus at the given level, connecting it the first time.
+    /// A level of 0 or less disconnects it and returns `None`.
+    ///
+    /// The change is made with the DSP network locked, so a new send is never heard at full level
+    /// before its mix is set.
+    pub fn set_send_level(&self, source: &Dsp,
+                          level: f32) -> Result<Option<DspConnection>, ::Status> {
+        match self.sys.lock_DSP() {
+            ::Status::Ok => {}
+            e => return Err(e)
+        }
+        let result = self.update_send(source, level);
+
+        match self.sys.unlock_DSP() {
+            ::Status::Ok => result,
+            e => Err(e)
+        }
+    }
+
+    fn update_send(&self, source: &Dsp, level: f32) -> Result<Option<DspConnection>, ::Status> {
+        let connection = ReturnBus::find_connection(&self.input, source)?;
+
+        if level <= 0f32 {
+            if connection.is_none() {
+                return Ok(None);
+            }
+            return match self.input.disconnect_from(ffi::FFI::wrap(ffi::FFI::unwrap(source))) {
+                ::Status::Ok => Ok(None),
+                e => Err(e)
+            };
+        }
+        let connection = match connection {
+            Some(c) => c,
+            None => self.input.add_input(ffi::FFI::wrap(ffi::FFI::unwrap(source)))?
+        };
+
+        match connection.set_mix(level) {
+            ::Status::Ok => Ok(Some(connection)),
+            e => Err(e)
+        }
+    }
+
+    /// Returns the send level of `source`, 0 if it doesn't send to the bus.
+    pub fn get_send_level(&self, source: &Dsp) -> Result<f32, ::Status> {
+        match ReturnBus::find_connection(&self.input, source) {
+            Ok(Some(connection)) => connection.get_mix(),
+            Ok(None) => Ok(0f32),
+            Err(e) => Err(e)
+        }
+    }
+}
+
+impl Drop for ReturnBus {
+    fn drop(&mut self) {
+        self.sys.lock_DSP();
+        self.input.disconnect_all(true, true);
+        for effect in self.effects.iter() {
+            effect.disconnect_all(true, true);
+        }
+        self.sys.unlock_DSP();
+    }
+}
@@ -146,3 +146,127 @@ impl Default for ReverbProperties {
         }
     }
 }
+
+/// Standard reverb environments, the same as the `FMOD_PRESET_*` macros of FMOD Ex. They convert
+/// into [`ReverbProperties`](struct.ReverbProperties.html), to be used with
+/// [`Sys::set_reverb_properties`](struct.Sys.html#method.set_reverb_properties),
+/// [`Sys::set_reverb_ambient_properties`](struct.Sys.html#method.set_reverb_ambient_properties) or
+/// [`Reverb::set_properties`](struct.Reverb.html#method.set_properties).
+///
+/// ```ignore
+/// sys.set_reverb_properties(rfmod::ReverbPreset::Cave.into());
+/// ```
+#[derive(Clone, PartialEq, PartialOrd, Debug, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ReverbPreset {
+    Off,
+    Generic,
+    PaddedCell,
+    Room,
+    Bathroom,
+    LivingRoom,
+    StoneRoom,
+    Auditorium,
+    ConcertHall,
+    Cave,
+    Arena,
+    Hangar,
+    CarpettedHallway,
+    Hallway,
+    StoneCorridor,
+    Alley,
+    Forest,
+    City,
+    Mountains,
+    Quarry,
+    Plain,
+    ParkingLot,
+    SewerPipe,
+    Underwater,
+}
+
+// environment, env_diffusion, room, room_HF, decay_time, decay_HF_ratio, reflections,
+// reflections_delay, reverb, reverb_delay, modulation_time, modulation_depth, diffusion, density,
+// flags. The other fields are the same for every preset.
+type PresetValues = (i32, f32, i32, i32, f32, f32, i32, f32, i32, f32, f32, f32, f32, f32, u32);
+
+static PRESETS: [PresetValues; 24] = [
+    (-1, 1.0, -10000, -10000, 1.0, 1.0, -2602, 0.007, 200, 0.011, 0.25, 0.0, 0.0, 0.0, 0x33f),
+    (0, 1.0, -1000, -100, 1.49, 0.83, -2602, 0.007, 200, 0.011, 0.25, 0.0, 100.0, 100.0, 0x3f),
+    (1, 1.0, -1000, -6000, 0.17, 0.1, -1204, 0.001, 207, 0.002, 0.25, 0.0, 100.0, 100.0, 0x3f),
+    (2, 1.0, -1000, -454, 0.4, 0.83, -1646, 0.002, 53, 0.003, 0.25, 0.0, 100.0, 100.0, 0x3f),
+    (3, 1.0, -1000, -1200, 1.49, 0.54, -370, 0.007, 1030, 0.011, 0.25, 0.0, 100.0, 60.0, 0x3f),
+    (4, 1.0, -1000, -6000, 0.5, 0.1, -1376, 0.003, -1104, 0.004, 0.25, 0.0, 100.0, 100.0, 0x3f),
+    (5, 1.0, -1000, -300, 2.31, 0.64, -711, 0.012, 83, 0.017, 0.25, 0.0, 100.0, 100.0, 0x3f),
+    (6, 1.0, -1000, -476, 4.32, 0.59, -789, 0.02, -289, 0.03, 0.25, 0.0, 100.0, 100.0, 0x3f),
+    (7, 1.0, -1000, -500, 3.92, 0.7, -1230, 0.02, -2, 0.029, 0.25, 0.0, 100.0, 100.0, 0x3f),
+    (8, 1.0, -1000, 0, 2.91, 1.3, -602, 0.015, -302, 0.022, 0.25, 0.0, 100.0, 100.0, 0x1f),
+    (9, 1.0, -1000, -698, 7.24, 0.33, -1166, 0.02, 16, 0.03, 0.25, 0.0, 100.0, 100.0, 0x3f),
+    (10, 1.0, -1000, -1000, 10.05, 0.23, -602, 0.02, 198, 0.03, 0.25, 0.0, 100.0, 100.0, 0x3f),
+    (11, 1.0, -1000, -4000, 0.3, 0.1, -1831, 0.002, -1630, 0.03, 0.25, 0.0, 100.0, 100.0, 0x3f),
+    (12, 1.0, -1000, -300, 1.49, 0.59, -1219, 0.007, 441, 0.011, 0.25, 0.0, 100.0, 100.0, 0x3f),
+    (13, 1.0, -1000, -237, 2.7, 0.79, -1214, 0.013, 395, 0.02, 0.25, 0.0, 100.0, 100.0, 0x3f),
+    (14, 0.3, -1000, -270, 1.49, 0.86, -1204, 0.007, -4, 0.011, 0.125, 0.95, 100.0, 100.0, 0x3f),
+    (15, 0.3, -1000, -3300, 1.49, 0.54, -2560, 0.162, -229, 0.088, 0.125, 1.0, 79.0, 100.0, 0x3f),
+    (16, 0.5, -1000, -800, 1.49, 0.67, -2273, 0.007, -1691, 0.011, 0.25, 0.0, 50.0, 100.0, 0x3f),
+    (17, 0.27, -1000, -2500, 1.49, 0.21, -2780, 0.3, -1434, 0.1, 0.25, 1.0, 27.0, 100.0, 0x1f),
+    (18, 1.0, -1000, -1000, 1.49, 0.83, -10000, 0.061, 500, 0.025, 0.125, 0.7, 100.0, 100.0, 0x3f),
+    (19, 0.21, -1000, -2000, 1.49, 0.5, -2466, 0.179, -1926, 0.1, 0.25, 1.0, 21.0, 100.0, 0x3f),
+    (20, 1.0, -1000, 0, 1.65, 1.5, -1363, 0.008, -1153, 0.012, 0.25, 0.0, 100.0, 100.0, 0x1f),
+    (21, 0.8, -1000, -1000, 2.81, 0.14, 429, 0.014, 1023, 0.021, 0.25, 0.0, 80.0, 60.0, 0x3f),
+    (22, 1.0, -1000, -4000, 1.49, 0.1, -449, 0.007, 1700, 0.011, 1.18, 0.348, 100.0, 100.0, 0x3f),
+];
+
+impl ReverbPreset {
+    /// Every preset, in FMOD order.
+    pub fn all() -> &'static [ReverbPreset] {
+        static ALL: [ReverbPreset; 24] = [
+            ReverbPreset::Off, ReverbPreset::Generic, ReverbPreset::PaddedCell, ReverbPreset::Room,
+            ReverbPreset::Bathroom, ReverbPreset::LivingRoom, ReverbPreset::StoneRoom,
+            ReverbPreset::Auditorium, ReverbPreset::ConcertHall, ReverbPreset::Cave,
+            ReverbPreset::Arena, ReverbPreset::Hangar, ReverbPreset::CarpettedHallway,
+            ReverbPreset::Hallway, ReverbPreset::StoneCorridor, ReverbPreset::Alley,
+            ReverbPreset::Forest, ReverbPreset::City, ReverbPreset::Mountains,
+            ReverbPreset::Quarry, ReverbPreset::Plain, ReverbPreset::ParkingLot,
+            ReverbPreset::SewerPipe, ReverbPreset::Underwater];
+
+        &ALL
+    }
+
+    pub fn get_properties(&self) -> ReverbProperties {
+        let (environment, env_diffusion, room, room_HF, decay_time, decay_HF_ratio, reflections,
+             reflections_delay, reverb, reverb_delay, modulation_time, modulation_depth,
+             diffusion, density, flags) = PRESETS[*self as usize];
+
+        ReverbProperties {
+            environment: environment,
+            env_diffusion: env_diffusion,
+            room: room,
+            room_HF: room_HF,
+            decay_time: decay_time,
+            decay_HF_ratio: decay_HF_ratio,
+            reflections: reflections,
+            reflections_delay: reflections_delay,
+            reverb: reverb,
+            reverb_delay: reverb_delay,
+            modulation_time: modulation_time,
+            modulation_depth: modulation_depth,
+            diffusion: diffusion,
+            density: density,
+            flags: flags,
+            .. Default::default()
+        }
+    }
+}
+
+impl From<ReverbPreset> for ReverbProperties {
+    fn from(preset: ReverbPreset) -> ReverbProperties {
+        preset.get_properties()
+    }
+}
+
+impl ReverbProperties {
+    pub fn from_preset(preset: ReverbPreset) -> ReverbProperties {
+        preset.get_properties()
+    }
+}
@@ -98,7 +98,6 @@ Here is the list of all modules :
 
 #![crate_name = "rfmod"]
 #![crate_type = "rlib"]
-#![crate_type = "dylib"]
 
 #![allow(non_camel_case_types)]
 #![allow(non_snake_case)]
@@ -108,9 +107,13 @@ Here is the list of all modules :
 extern crate libc;
 extern crate c_vec;
 extern crate byteorder;
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde;
 
 pub use channel::{
     Channel,
+    ChannelState,
     SpeakerMixOptions,
     ReverbChannelProperties
 };
@@ -130,20 +133,128 @@ pub use sound::{
     FmodSyncPoint
 };
 pub use channel_group::{
-    ChannelGroup
+    ChannelGroup,
+    ChannelGroups,
+    Channels,
+    Walk
+};
+pub use sound_group::{
+    SoundGroup,
+    Sounds
+};
+pub use sound_bank::{
+    SoundBank,
+    SoundManifest,
+    SoundEntry,
+    SoundDefaults
+};
+pub use audible_policy::{
+    AudiblePolicy,
+    AudibleDecision,
+    StealFarthest,
+    StealOldest,
+    StealQuietest,
+    Cooldown
 };
-pub use sound_group::SoundGroup;
 pub use dsp::{
     Dsp,
     DspParameterDesc,
+    ParameterInfo,
+    DspInfo,
+    DspParameterKind,
     DspDescription,
-    DspState
+    DspState,
+    DspProcessor
+};
+pub use dsp_effects::{
+    LowPass,
+    Echo,
+    Compressor,
+    SfxReverb,
+    ParamEq,
+    PitchShift,
+    Chorus,
+    Flange,
+    Distortion,
+    Normalize
 };
 pub use dsp_connection::DspConnection;
+pub use dsp_chain::{
+    DspChain,
+    MAX_DSP_DEPTH
+};
+pub use dsp_harness::{
+    DspHarness,
+    TestSignal,
+    TestBuffer
+};
+pub use dsp_automation::{
+    Automation,
+    AutomationLane,
+    Automator,
+    LfoShape
+};
+pub use dsp_preset::{
+    DspPreset,
+    DspSendPreset,
+    DspChainPreset
+};
+pub use return_bus::ReturnBus;
+pub use dsp_graph::{
+    DspGraph,
+    DspNode,
+    DspNodeParameter,
+    DspEdge
+};
 pub use reverb::Reverb;
-pub use reverb_properties::ReverbProperties;
+pub use reverb_properties::{
+    ReverbProperties,
+    ReverbPreset
+};
 pub use vector::Vector;
-pub use geometry::Geometry;
+pub use geometry::{
+    Geometry,
+    OcclusionMaterial,
+    Polygon,
+    Polygons,
+    PolygonMut
+};
+pub use geometry_builder::{
+    GeometryBuilder,
+    GeometryTransform,
+    Primitive,
+    Door,
+    RoomWall
+};
+pub use occlusion::{
+    OcclusionMirror,
+    OcclusionQuery,
+    OcclusionHit,
+    MirrorPolygon
+};
+pub use geometry_obj::MaterialMap;
+pub use speaker_matrix::{
+    SpeakerMatrix,
+    MAX_INPUT_CHANNELS
+};
+pub use mixer::{
+    Mixer,
+    BusDescription,
+    EffectDescription
+};
+pub use ducker::{
+    Ducker,
+    DuckDetection
+};
+pub use mix_snapshot::{
+    MixSnapshot,
+    MixBlender
+};
+pub use voice_manager::{
+    VoiceManager,
+    StealPolicy,
+    VoiceStats
+};
 pub use file::{
     FmodFile,
     SeekStyle
@@ -209,11 +320,28 @@ mod fmod_sys;
 mod dsp;
 mod dsp_connection;
 mod geometry;
+mod geometry_obj;
+mod geometry_builder;
+mod occlusion;
 mod vector;
 mod reverb;
 mod reverb_properties;
 mod file;
 mod enums;
+mod voice_manager;
+mod speaker_matrix;
+mod mixer;
+mod ducker;
+mod mix_snapshot;
+mod audible_policy;
+mod sound_bank;
+mod dsp_effects;
+mod dsp_graph;
+mod dsp_chain;
+mod dsp_harness;
+mod dsp_automation;
+mod dsp_preset;
+mod return_bus;
 pub mod types;
 pub mod callbacks;
 pub mod error;
@@ -462,7 +590,8 @@ pub const EVENT_MEMBITS_EVENTINSTANCE_GROUP  : u32 = EVENT_MEMBITS_EVENTINSTANCE
 /// All sound definition memory
 pub const EVENT_MEMBITS_SOUNDDEF_GROUP       : u32 = EVENT_MEMBITS_SOUNDDEFCLASS | EVENT_MEMBITS_SOUNDDEFDEFCLASS | EVENT_MEMBITS_SOUNDDEFPOOL;
 
-#[cfg(target_os = "linux")]
+// The unit tests don't call FMOD, so they don't link it.
+#[cfg(all(not(test), target_os = "linux"))]
 mod platform {
     #[cfg(target_arch="x86")]
     #[link(name = "fmodex")] extern{}
@@ -470,12 +599,12 @@ mod platform {
     #[link(name = "fmodex64")] extern{}
 }
 
-#[cfg(target_os = "macos")]
+#[cfg(all(not(test), target_os = "macos"))]
 mod platform {
     #[link(name = "fmodex")] extern{}
 }
 
-#[cfg(target_os = "windows")]
+#[cfg(all(not(test), target_os = "windows"))]
 mod platform {
     #[cfg(target_arch="x86")]
     #[link(name = "fmodex_vc")] extern{}
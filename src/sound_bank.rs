@@ -0,0 +1,403 @@
+/*
+* Rust-FMOD - Copyright (c) 2018 Gomez Guillaume.
+*
+* The Original software, FmodEx library, is provided by FIRELIGHT TECHNOLOGIES.
+*
+* This software is provided 'as-is', without any express or implied warranty.
+* In no event will the authors be held liable for any damages arising from
+* the use of this software.
+*
+* Permission is granted to anyone to use this software for any purpose,
+* including commercial applications, and to alter it and redistribute it
+* freely, subject to the following restrictions:
+*
+* 1. The origin of this software must not be misrepresented; you must not claim
+*    that you wrote the original software. If you use this software in a product,
+*    an acknowledgment in the product documentation would be appreciated but is
+*    not required.
+*
+* 2. Altered source versions must be plainly marked as such, and must not be
+*    misrepresented as being the original software.
+*
+* 3. This notice may not be removed or altered from any source distribution.
+*/
+
+use ffi;
+use channel::Channel;
+use fmod_sys::Sys;
+use sound::Sound;
+use sound_group::SoundGroup;
+use types::{Mode, EventMemoryBits};
+use std::collections::HashMap;
+use std::default::Default;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+/// Default playback values of a sound, given to
+/// [`Sound::set_defaults`](struct.Sound.html#method.set_defaults).
+pub struct SoundDefaults {
+    pub frequency: f32,
+    pub volume   : f32,
+    pub pan      : f32,
+    pub priority : i32,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+/// Description of a sound in a [`SoundManifest`](struct.SoundManifest.html).
+pub struct SoundEntry {
+    /// Path of the sound file.
+    pub path       : String,
+    /// Other files played in turn with `path`.
+    pub variations : Vec<String>,
+    /// Mode flags given to [`Sys::create_sound`](struct.Sys.html#method.create_sound).
+    pub mode       : u32,
+    /// Opens the sound with [`Sys::create_stream`](struct.Sys.html#method.create_stream) instead
+    /// of loading it in memory.
+    pub stream     : bool,
+    /// Name of the sound group the sound is put in. The group is created by the bank.
+    pub sound_group: Option<String>,
+    pub defaults   : Option<SoundDefaults>,
+}
+
+impl Default for SoundEntry {
+    fn default() -> SoundEntry {
+        SoundEntry {
+            path: String::new(),
+            variations: Vec::new(),
+            mode: ::DEFAULT,
+            stream: false,
+            sound_group: None,
+            defaults: None,
+        }
+    }
+}
+
+impl SoundEntry {
+    pub fn new(path: &str) -> SoundEntry {
+        SoundEntry {
+            path: path.to_owned(),
+            .. Default::default()
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+/// Sounds known by a [`SoundBank`](struct.SoundBank.html), by logical id.
+pub struct SoundManifest {
+    pub entries: HashMap<String, SoundEntry>,
+}
+
+impl SoundManifest {
+    pub fn new() -> SoundManifest {
+        Default::default()
+    }
+
+    pub fn add(&mut self, id: &str, entry: SoundEntry) {
+        self.entries.insert(id.to_owned(), entry);
+    }
+}
+
+struct Slot {
+    entry: SoundEntry,
+    sounds: Vec<Sound>,
+    memory: u32,
+    refs: usize,
+    last_used: u64,
+    next_variation: usize,
+}
+
+impl Slot {
+    fn unload(&mut self) -> u32 {
+        let memory = self.memory;
+
+        // dropping the sounds releases them
+        self.sounds.clear();
+        self.memory = 0;
+        memory
+    }
+}
+
+/// Registry loading sounds by logical id from a [`SoundManifest`](struct.SoundManifest.html).
+///
+/// Sounds are loaded on first use. When a memory budget is set, the least recently used sounds
+/// which are not acquired are released until the memory used by the bank, measured with
+/// [`Sound::get_memory_info`](struct.Sound.html#method.get_memory_info), fits in it again.
+/// Releasing a sound stops the channels playing it.
+///
+/// ```ignore
+/// let mut bank = rfmod::SoundBank::new(&fmod, manifest);
+///
+/// bank.set_memory_budget(Some(32 * 1024 * 1024));
+/// bank.play("weapons/shotgun");
+/// ```
+pub struct SoundBank {
+    sys: Sys,
+    slots: HashMap<String, Slot>,
+    groups: HashMap<String, SoundGroup>,
+    budget: Option<u32>,
+    memory: u32,
+    clock: u64,
+}
+
+impl SoundBank {
+    pub fn new(sys: &Sys, manifest: SoundManifest) -> SoundBank {
+        let mut bank = SoundBank {
+            sys: ffi::FFI::wrap(ffi::FFI::unwrap(sys)),
+            slots: HashMap::new(),
+            groups: HashMap::new(),
+            budget: None,
+            memory: 0,
+            clock: 0,
+        };
+
+        for (id, entry) in manifest.entries.into_iter() {
+            bank.add(&id, entry);
+        }
+        bank
+    }
+
+    /// Adds or replaces an entry. A replaced entry is unloaded.
+    pub fn add(&mut self, id: &str, entry: SoundEntry) {
+        let slot = Slot {
+            entry: entry,
+            sounds: Vec::new(),
+            memory: 0,
+            refs: 0,
+            last_used: 0,
+            next_variation: 0,
+        };
+
+        if let Some(mut old) = self.slots.insert(id.to_owned(), slot) {
+            self.memory -= old.unload();
+        }
+    }
+
+    /// Unloads and forgets the entry.
+    pub fn remove(&mut self, id: &str) -> ::Status {
+        match self.slots.remove(id) {
+            Some(mut slot) => {
+                self.memory -= slot.unload();
+                ::Status::Ok
+            }
+            None => ::Status::InvalidParam
+        }
+    }
+
+    /// Sets the maximum memory, in bytes, the loaded sounds may use. `None` disables it.
+    pub fn set_memory_budget(&mut self, budget: Option<u32>) -> ::Status {
+        self.budget = budget;
+        self.enforce_budget(None)
+    }
+
+    pub fn get_memory_budget(&self) -> Option<u32> {
+        self.budget
+    }
+
+    /// Returns the memory used by the sounds loaded by the bank, in bytes.
+    pub fn get_memory_used(&self) -> u32 {
+        self.memory
+    }
+
+    /// Returns (currently allocated, max allocated, total) sound RAM of the system, see
+    /// [`Sys::get_sound_RAM`](struct.Sys.html#method.get_sound_RAM).
+    pub fn get_sound_RAM(&self) -> Result<(i32, i32, i32), ::Status> {
+        self.sys.get_sound_RAM()
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.slots.contains_key(id)
+    }
+
+    pub fn is_loaded(&self, id: &str) -> bool {
+        match self.slots.get(id) {
+            Some(slot) => !slot.sounds.is_empty(),
+            None => false
+        }
+    }
+
+    pub fn get_ref_count(&self, id: &str) -> Option<usize> {
+        self.slots.get(id).map(|slot| slot.refs)
+    }
+
+    /// Returns the loaded sounds of the entry, the main one first.
+    pub fn get_sounds(&self, id: &str) -> Option<&[Sound]> {
+        match self.slots.get(id) {
+            Some(slot) if !slot.sounds.is_empty() => Some(&slot.sounds[..]),
+            _ => None
+        }
+    }
+
+    /// Loads the entry if needed. Returns `Status::Memory` if the budget couldn't be met, the
+    /// entry stays loaded anyway.
+    pub fn load(&mut self, id: &str) -> ::Status {
+        match self.load_slot(id) {
+            ::Status::Ok => self.enforce_budget(Some(id)),
+            e => e
+        }
+    }
+
+    /// Loads the entry and keeps it from being evicted until
+    /// [`release`](#method.release) is called as many times.
+    pub fn acquire(&mut self, id: &str) -> ::Status {
+        match self.load_slot(id) {
+            ::Status::Ok => {
+                if let Some(slot) = self.slots.get_mut(id) {
+                    slot.refs += 1;
+                }
+                self.enforce_budget(Some(id))
+            }
+            e => e
+        }
+    }
+
+    pub fn release(&mut self, id: &str) -> ::Status {
+        match self.slots.get_mut(id) {
+            Some(ref mut slot) if slot.refs > 0 => {
+                slot.refs -= 1;
+                ::Status::Ok
+            }
+            _ => ::Status::InvalidParam
+        }
+    }
+
+    /// Releases the sounds of the entry. Returns `Status::InvalidParam` if it is still acquired.
+    pub fn unload(&mut self, id: &str) -> ::Status {
+        match self.slots.get_mut(id) {
+            Some(slot) if slot.refs == 0 => {
+                self.memory -= slot.unload();
+                ::Status::Ok
+            }
+            _ => ::Status::InvalidParam
+        }
+    }
+
+    /// Releases every sound which is not acquired.
+    pub fn unload_unused(&mut self) {
+        for slot in self.slots.values_mut().filter(|s| s.refs == 0) {
+            self.memory -= slot.unload();
+        }
+    }
+
+    /// Plays the entry, loading it first if needed. Variations are played in turn.
+    ///
+    /// The sound is played even if the budget couldn't be met.
+    pub fn play(&mut self, id: &str) -> Result<Channel, ::Status> {
+        match self.load_slot(id) {
+            ::Status::Ok => {}
+            e => return Err(e)
+        }
+        self.enforce_budget(Some(id));
+        match self.slots.get_mut(id) {
+            Some(slot) => {
+                let index = slot.next_variation % slot.sounds.len();
+
+                slot.next_variation = index + 1;
+                slot.sounds[index].play()
+            }
+            None => Err(::Status::InvalidParam)
+        }
+    }
+
+    fn load_slot(&mut self, id: &str) -> ::Status {
+        self.clock += 1;
+
+        let clock = self.clock;
+        let slot = match self.slots.get_mut(id) {
+            Some(s) => s,
+            None => return ::Status::InvalidParam
+        };
+
+        slot.last_used = clock;
+        if !slot.sounds.is_empty() {
+            return ::Status::Ok;
+        }
+
+        let group = match slot.entry.sound_group {
+            Some(ref name) => {
+                if !self.groups.contains_key(name) {
+                    match self.sys.create_sound_group(name) {
+                        Ok(g) => { self.groups.insert(name.clone(), g); }
+                        Err(e) => return e
+                    }
+                }
+                self.groups.get(name)
+            }
+            None => None
+        };
+        let mut sounds = Vec::with_capacity(slot.entry.variations.len() + 1);
+        let mut memory = 0u32;
+
+        for path in Some(&slot.entry.path).into_iter().chain(slot.entry.variations.iter()) {
+            let sound = match if slot.entry.stream {
+                self.sys.create_stream(path, Some(Mode(slot.entry.mode)), None)
+            } else {
+                self.sys.create_sound(path, Some(Mode(slot.entry.mode)), None)
+            } {
+                Ok(s) => s,
+                Err(e) => return e
+            };
+
+            if let Some(group) = group {
+                match sound.set_sound_group(ffi::FFI::wrap(ffi::FFI::unwrap(group))) {
+                    ::Status::Ok => {}
+                    e => return e
+                }
+            }
+            if let Some(d) = slot.entry.defaults {
+                match sound.set_defaults(d.frequency, d.volume, d.pan, d.priority) {
+                    ::Status::Ok => {}
+                    e => return e
+                }
+            }
+            memory += match sound.get_memory_info(::MEMBITS_ALL, EventMemoryBits(0)) {
+                Ok((used, _)) => used,
+                Err(e) => return e
+            };
+            sounds.push(sound);
+        }
+        slot.sounds = sounds;
+        slot.memory = memory;
+        self.memory += memory;
+        ::Status::Ok
+    }
+
+    /// Evicts the least recently used entries which are not acquired, except `keep`, until the
+    /// memory used fits in the budget.
+    fn enforce_budget(&mut self, keep: Option<&str>) -> ::Status {
+        let budget = match self.budget {
+            Some(b) => b,
+            None => return ::Status::Ok
+        };
+
+        while self.memory > budget {
+            let victim = self.slots.iter()
+                                   .filter(|&(id, s)| {
+                                       s.refs == 0 && !s.sounds.is_empty() &&
+                                       Some(id.as_str()) != keep
+                                   })
+                                   .min_by_key(|&(_, s)| s.last_used)
+                                   .map(|(id, _)| id.clone());
+
+            match victim {
+                Some(id) => {
+                    if let Some(slot) = self.slots.get_mut(&id) {
+                        self.memory -= slot.unload();
+                    }
+                }
+                None => return ::Status::Memory
+            }
+        }
+        ::Status::Ok
+    }
+}
+
+impl Drop for SoundBank {
+    fn drop(&mut self) {
+        // sounds have to be released before their groups
+        self.slots.clear();
+        self.groups.clear();
+    }
+}
@@ -31,15 +31,26 @@ use fmod_sys::MemoryUsageDetails;
 use std::mem::transmute;
 use libc::{c_char};
 use std::default::Default;
+use channel;
+use audible_policy::{AudiblePolicy, AudibleDecision};
 
 /// SoundGroup object
+///
+/// Only the group returned by
+/// [`Sys::create_sound_group`](struct.Sys.html#method.create_sound_group) owns the FMOD group:
+/// it is released when dropped. The groups returned by
+/// [`Sound::get_sound_group`](struct.Sound.html#method.get_sound_group) and
+/// [`Sys::get_master_sound_group`](struct.Sys.html#method.get_master_sound_group) are handles on a
+/// group owned by someone else, dropping them or calling [`release`](#method.release) on them
+/// does nothing.
 pub struct SoundGroup {
     sound_group: *mut ffi::FMOD_SOUNDGROUP,
+    can_be_deleted: bool,
 }
 
 impl ffi::FFI<ffi::FMOD_SOUNDGROUP> for SoundGroup {
     fn wrap(s: *mut ffi::FMOD_SOUNDGROUP) -> SoundGroup {
-        SoundGroup {sound_group: s}
+        SoundGroup {sound_group: s, can_be_deleted: false}
     }
 
     fn unwrap(s: &SoundGroup) -> *mut ffi::FMOD_SOUNDGROUP {
@@ -53,9 +64,40 @@ impl Drop for SoundGroup {
     }
 }
 
+pub fn from_ptr_first(sound_group: *mut ffi::FMOD_SOUNDGROUP) -> SoundGroup {
+    SoundGroup {sound_group: sound_group, can_be_deleted: true}
+}
+
+/// Iterator over the sounds of a [`SoundGroup`](struct.SoundGroup.html), returned by
+/// [`SoundGroup::sounds`](struct.SoundGroup.html#method.sounds).
+pub struct Sounds {
+    sound_group: *mut ffi::FMOD_SOUNDGROUP,
+    index: i32,
+    count: i32,
+}
+
+impl Iterator for Sounds {
+    type Item = sound::Sound;
+
+    fn next(&mut self) -> Option<sound::Sound> {
+        while self.index < self.count {
+            let mut sound = ::std::ptr::null_mut();
+            let index = self.index;
+
+            self.index += 1;
+            match unsafe { ffi::FMOD_SoundGroup_GetSound(self.sound_group, index, &mut sound) } {
+                ::Status::Ok if !sound.is_null() => return Some(ffi::FFI::wrap(sound)),
+                _ => {}
+            }
+        }
+        None
+    }
+}
+
 impl SoundGroup {
+    /// Releases the group if this handle owns it, see [`SoundGroup`](struct.SoundGroup.html).
     pub fn release(&mut self) -> ::Status {
-        if !self.sound_group.is_null() {
+        if self.can_be_deleted && !self.sound_group.is_null() {
             match unsafe { ffi::FMOD_SoundGroup_Release(self.sound_group) } {
                ::Status::Ok => {
                     self.sound_group =::std::ptr::null_mut();
@@ -158,6 +200,91 @@ impl SoundGroup {
         }
     }
 
+    /// Iterates over the sounds of the group.
+    pub fn sounds(&self) -> Sounds {
+        Sounds {
+            sound_group: self.sound_group,
+            index: 0,
+            count: self.get_num_sounds().unwrap_or(0),
+        }
+    }
+
+    /// Iterates over the channels currently playing a sound of the group, found by walking the
+    /// channel group tree of the system.
+    pub fn playing_channels(&self) -> ::std::vec::IntoIter<channel::Channel> {
+        let mut channels = Vec::new();
+        let master = match self.sounds().next() {
+            Some(sound) => match sound.get_system_object() {
+                Ok(sys) => sys.get_master_channel_group(),
+                Err(e) => Err(e)
+            },
+            None => Err(::Status::InvalidHandle)
+        };
+
+        if let Ok(master) = master {
+            for (_, group) in master.walk() {
+                for channel in group.channels() {
+                    let mut sound_group = ::std::ptr::null_mut();
+                    let belongs = match channel.get_current_sound() {
+                        Ok(sound) => unsafe {
+                            ffi::FMOD_Sound_GetSoundGroup(ffi::FFI::unwrap(&sound),
+                                                          &mut sound_group) == ::Status::Ok
+                        },
+                        Err(_) => false
+                    };
+
+                    if belongs && sound_group == self.sound_group {
+                        channels.push(channel);
+                    }
+                }
+            }
+        }
+        channels.into_iter()
+    }
+
+    /// Plays a sound of the group after asking the policy whether it may start.
+    ///
+    /// The policy is given the channels currently playing in the group and the value of
+    /// [`get_max_audible`](#method.get_max_audible). Returns `Err(Status::ChannelAlloc)` if the
+    /// policy refused the sound and `Err(Status::InvalidParam)` if the sound isn't part of the
+    /// group.
+    pub fn play_with_policy<P: AudiblePolicy>(&self, sound: &sound::Sound, policy: &mut P)
+                                              -> Result<channel::Channel, ::Status> {
+        let mut sound_group = ::std::ptr::null_mut();
+
+        match unsafe { ffi::FMOD_Sound_GetSoundGroup(ffi::FFI::unwrap(sound), &mut sound_group) } {
+            ::Status::Ok if sound_group == self.sound_group => {}
+            ::Status::Ok => return Err(::Status::InvalidParam),
+            e => return Err(e)
+        }
+
+        let max_audible = match self.get_max_audible() {
+            Ok(m) if m >= 0 => Some(m as usize),
+            Ok(_) => None,
+            Err(e) => return Err(e)
+        };
+        let playing : Vec<channel::Channel> = self.playing_channels().collect();
+
+        match policy.evaluate(sound, &playing, max_audible) {
+            AudibleDecision::Play => {}
+            AudibleDecision::Steal(pos) if pos < playing.len() => {
+                match playing[pos].stop() {
+                    ::Status::Ok => {}
+                    e => return Err(e)
+                }
+            }
+            AudibleDecision::Steal(_) => return Err(::Status::InvalidParam),
+            AudibleDecision::Reject => return Err(::Status::ChannelAlloc)
+        }
+        match sound.play() {
+            Ok(channel) => {
+                policy.on_started(sound, &channel);
+                Ok(channel)
+            }
+            Err(e) => Err(e)
+        }
+    }
+
     pub fn get_num_playing(&self) -> Result<i32, ::Status> {
         let mut num_playing = 0i32;
 
@@ -0,0 +1,270 @@
+/*
+* Rust-FMOD - Copyright (c) 2018 Gomez Guillaume.
+*
+* The Original software, FmodEx library, is provided by FIRELIGHT TECHNOLOGIES.
+*
+* This software is provided 'as-is', without any express or implied warranty.
+* In no event will the authors be held liable for any damages arising from
+* the use of this software.
+*
+* Permission is granted to anyone to use this software for any purpose,
+* including commercial applications, and to alter it and redistribute it
+* freely, subject to the following restrictions:
+*
+* 1. The origin of this software must not be misrepresented; you must not claim
+*    that you wrote the original software. If you use this software in a product,
+*    an acknowledgment in the product documentation would be appreciated but is
+*    not required.
+*
+* 2. Altered source versions must be plainly marked as such, and must not be
+*    misrepresented as being the original software.
+*
+* 3. This notice may not be removed or altered from any source distribution.
+*/
+
+use channel::SpeakerMixOptions;
+use std::ops::{Index, IndexMut};
+
+/// Maximum number of input channels FMOD accepts in a speaker level array.
+pub const MAX_INPUT_CHANNELS: usize = 16;
+
+static MONO: [::Speaker; 1] = [::Speaker::FrontLeft];
+static STEREO: [::Speaker; 2] = [::Speaker::FrontLeft, ::Speaker::FrontRight];
+static QUAD: [::Speaker; 4] = [::Speaker::FrontLeft, ::Speaker::FrontRight, ::Speaker::BackLeft,
+                               ::Speaker::BackRight];
+static SURROUND: [::Speaker; 5] = [::Speaker::FrontLeft, ::Speaker::FrontRight,
+                                   ::Speaker::FrontCenter, ::Speaker::BackLeft,
+                                   ::Speaker::BackRight];
+static FIVE_POINT_ONE: [::Speaker; 6] = [::Speaker::FrontLeft, ::Speaker::FrontRight,
+                                         ::Speaker::FrontCenter, ::Speaker::LowFrequency,
+                                         ::Speaker::BackLeft, ::Speaker::BackRight];
+static SEVEN_POINT_ONE: [::Speaker; 8] = [::Speaker::FrontLeft, ::Speaker::FrontRight,
+                                          ::Speaker::FrontCenter, ::Speaker::LowFrequency,
+                                          ::Speaker::BackLeft, ::Speaker::BackRight,
+                                          ::Speaker::SideLeft, ::Speaker::SideRight];
+
+/// Returns the speakers used by the given speaker mode, in FMOD order. `Raw`, `Max` and `ForceInt`
+/// don't have a fixed speaker layout and return `None`.
+pub fn get_speakers(speaker_mode: ::SpeakerMode) -> Option<&'static [::Speaker]> {
+    match speaker_mode {
+        ::SpeakerMode::Mono => Some(&MONO),
+        ::SpeakerMode::Stereo => Some(&STEREO),
+        ::SpeakerMode::Quad => Some(&QUAD),
+        ::SpeakerMode::Surround => Some(&SURROUND),
+        ::SpeakerMode::_5Point1 | ::SpeakerMode::SRS5_1_Matrix
+            | ::SpeakerMode::DOLBY5_1_Matrix => Some(&FIVE_POINT_ONE),
+        ::SpeakerMode::_7Point1 | ::SpeakerMode::MYears => Some(&SEVEN_POINT_ONE),
+        _ => None
+    }
+}
+
+/// Levels sent from each input channel of a sound to each speaker of the output.
+///
+/// The matrix is sized from the number of input channels of the sound and from the speakers of a
+/// [`SpeakerMode`](enums/fmod/type.SpeakerMode.html). It is used with
+/// [`Channel::set_speaker_matrix`](struct.Channel.html#method.set_speaker_matrix) and
+/// [`DspConnection::set_speaker_matrix`](struct.DspConnection.html#method.set_speaker_matrix).
+///
+/// ```ignore
+/// let mut matrix = channel.get_speaker_matrix().unwrap();
+///
+/// matrix[(rfmod::Speaker::FrontLeft, 0)] = 1f32;
+/// matrix[(rfmod::Speaker::FrontRight, 0)] = 0f32;
+/// channel.set_speaker_matrix(&matrix);
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct SpeakerMatrix {
+    speaker_mode: ::SpeakerMode,
+    speakers: &'static [::Speaker],
+    input_channels: usize,
+    levels: Vec<f32>,
+}
+
+impl SpeakerMatrix {
+    /// Creates a matrix with all levels set to 0.
+    ///
+    /// Returns `Err(Status::InvalidParam)` if `input_channels` is 0 or greater than
+    /// [`MAX_INPUT_CHANNELS`](constant.MAX_INPUT_CHANNELS.html) and `Err(Status::Unsupported)` if
+    /// the speaker mode has no fixed speaker layout.
+    pub fn new(speaker_mode: ::SpeakerMode,
+               input_channels: usize) -> Result<SpeakerMatrix, ::Status> {
+        if input_channels == 0 || input_channels > MAX_INPUT_CHANNELS {
+            return Err(::Status::InvalidParam);
+        }
+        match get_speakers(speaker_mode) {
+            Some(speakers) => Ok(SpeakerMatrix {
+                speaker_mode: speaker_mode,
+                speakers: speakers,
+                input_channels: input_channels,
+                levels: vec![0f32; speakers.len() * input_channels],
+            }),
+            None => Err(::Status::Unsupported)
+        }
+    }
+
+    /// Creates a matrix where every input channel goes to the speaker of the same index at full
+    /// volume.
+    pub fn identity(speaker_mode: ::SpeakerMode,
+                    input_channels: usize) -> Result<SpeakerMatrix, ::Status> {
+        let mut matrix = SpeakerMatrix::new(speaker_mode, input_channels)?;
+
+        for it in 0..::std::cmp::min(matrix.speakers.len(), input_channels) {
+            matrix.levels[it * input_channels + it] = 1f32;
+        }
+        Ok(matrix)
+    }
+
+    /// Creates a matrix for a mono input from a
+    /// [`SpeakerMixOptions`](struct.SpeakerMixOptions.html). The levels of speakers which are not
+    /// part of the speaker mode are ignored.
+    pub fn from_speaker_mix(speaker_mode: ::SpeakerMode,
+                            smo: &SpeakerMixOptions) -> Result<SpeakerMatrix, ::Status> {
+        let mut matrix = SpeakerMatrix::new(speaker_mode, 1)?;
+
+        for pos in 0..matrix.speakers.len() {
+            matrix.levels[pos] = speaker_mix_level(smo, matrix.speakers[pos]);
+        }
+        Ok(matrix)
+    }
+
+    /// Returns the levels of the given input channel as a
+    /// [`SpeakerMixOptions`](struct.SpeakerMixOptions.html). Speakers which are not part of the
+    /// speaker mode are set to 0.
+    pub fn to_speaker_mix(&self, input_channel: usize) -> Option<SpeakerMixOptions> {
+        if input_channel >= self.input_channels {
+            return None;
+        }
+        let mut smo : SpeakerMixOptions = Default::default();
+
+        for (pos, speaker) in self.speakers.iter().enumerate() {
+            let level = self.levels[pos * self.input_channels + input_channel];
+
+            match *speaker {
+                ::Speaker::FrontLeft => smo.front_left = level,
+                ::Speaker::FrontRight => smo.front_right = level,
+                ::Speaker::FrontCenter => smo.center = level,
+                ::Speaker::LowFrequency => smo.lfe = level,
+                ::Speaker::BackLeft => smo.back_left = level,
+                ::Speaker::BackRight => smo.back_right = level,
+                ::Speaker::SideLeft => smo.side_left = level,
+                ::Speaker::SideRight => smo.side_right = level,
+                _ => {}
+            }
+        }
+        Some(smo)
+    }
+
+    pub fn get_speaker_mode(&self) -> ::SpeakerMode {
+        self.speaker_mode
+    }
+
+    /// Speakers of the matrix, in FMOD order.
+    pub fn get_speakers(&self) -> &'static [::Speaker] {
+        self.speakers
+    }
+
+    pub fn get_input_channels(&self) -> usize {
+        self.input_channels
+    }
+
+    fn speaker_pos(&self, speaker: ::Speaker) -> Option<usize> {
+        self.speakers.iter().position(|s| *s == speaker)
+    }
+
+    /// Returns the levels of every input channel for the given speaker or `None` if the speaker
+    /// isn't part of the speaker mode.
+    pub fn get_levels(&self, speaker: ::Speaker) -> Option<&[f32]> {
+        self.speaker_pos(speaker).map(|pos| {
+            &self.levels[pos * self.input_channels..(pos + 1) * self.input_channels]
+        })
+    }
+
+    /// Sets the levels of every input channel for the given speaker.
+    ///
+    /// Returns `Status::InvalidSpeaker` if the speaker isn't part of the speaker mode and
+    /// `Status::InvalidParam` if `levels` doesn't contain exactly one level per input channel.
+    pub fn set_levels(&mut self, speaker: ::Speaker, levels: &[f32]) -> ::Status {
+        if levels.len() != self.input_channels {
+            return ::Status::InvalidParam;
+        }
+        match self.speaker_pos(speaker) {
+            Some(pos) => {
+                let start = pos * self.input_channels;
+
+                self.levels[start..start + self.input_channels].copy_from_slice(levels);
+                ::Status::Ok
+            }
+            None => ::Status::InvalidSpeaker
+        }
+    }
+
+    pub fn get(&self, speaker: ::Speaker, input_channel: usize) -> Option<f32> {
+        if input_channel >= self.input_channels {
+            return None;
+        }
+        self.speaker_pos(speaker).map(|pos| self.levels[pos * self.input_channels + input_channel])
+    }
+
+    pub fn set(&mut self, speaker: ::Speaker, input_channel: usize, level: f32) -> ::Status {
+        if input_channel >= self.input_channels {
+            return ::Status::InvalidParam;
+        }
+        match self.speaker_pos(speaker) {
+            Some(pos) => {
+                self.levels[pos * self.input_channels + input_channel] = level;
+                ::Status::Ok
+            }
+            None => ::Status::InvalidSpeaker
+        }
+    }
+
+    /// Checks that the matrix can be sent to FMOD: it must match the given speaker mode and input
+    /// channel count and all levels must be finite and positive.
+    pub fn validate(&self, speaker_mode: ::SpeakerMode, input_channels: usize) -> ::Status {
+        if get_speakers(speaker_mode) != Some(self.speakers) {
+            ::Status::InvalidSpeaker
+        } else if input_channels != self.input_channels {
+            ::Status::InvalidParam
+        } else if self.levels.iter().any(|l| !l.is_finite() || *l < 0f32) {
+            ::Status::InvalidFloat
+        } else {
+            ::Status::Ok
+        }
+    }
+}
+
+impl Index<(::Speaker, usize)> for SpeakerMatrix {
+    type Output = f32;
+
+    fn index(&self, (speaker, input_channel): (::Speaker, usize)) -> &f32 {
+        assert!(input_channel < self.input_channels, "input channel out of bounds");
+        match self.speaker_pos(speaker) {
+            Some(pos) => &self.levels[pos * self.input_channels + input_channel],
+            None => panic!("{:?} is not part of {:?}", speaker, self.speaker_mode)
+        }
+    }
+}
+
+impl IndexMut<(::Speaker, usize)> for SpeakerMatrix {
+    fn index_mut(&mut self, (speaker, input_channel): (::Speaker, usize)) -> &mut f32 {
+        assert!(input_channel < self.input_channels, "input channel out of bounds");
+        match self.speaker_pos(speaker) {
+            Some(pos) => &mut self.levels[pos * self.input_channels + input_channel],
+            None => panic!("{:?} is not part of {:?}", speaker, self.speaker_mode)
+        }
+    }
+}
+
+fn speaker_mix_level(smo: &SpeakerMixOptions, speaker: ::Speaker) -> f32 {
+    match speaker {
+        ::Speaker::FrontLeft => smo.front_left,
+        ::Speaker::FrontRight => smo.front_right,
+        ::Speaker::FrontCenter => smo.center,
+        ::Speaker::LowFrequency => smo.lfe,
+        ::Speaker::BackLeft => smo.back_left,
+        ::Speaker::BackRight => smo.back_right,
+        ::Speaker::SideLeft => smo.side_left,
+        ::Speaker::SideRight => smo.side_right,
+        _ => 0f32
+    }
+}
@@ -37,3 +37,30 @@ macro_rules! from_utf8 {
         String::from_utf8_lossy(&$vec).into()
     }}
 }
+
+/// Escapes a string so it can be put between double quotes in JSON or Graphviz DOT.
+pub fn escape_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c)
+        }
+    }
+    out
+}
+
+/// Formats a float for JSON, which has no representation for NaN and infinities.
+pub fn json_float(value: f32) -> String {
+    if value.is_finite() {
+        format!("{}", value)
+    } else {
+        "null".to_owned()
+    }
+}
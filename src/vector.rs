@@ -42,6 +42,7 @@ pub fn get_ffi(vec: &Vector) -> ffi::FMOD_VECTOR {
 }
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 /// Structure describing a point in 3D space.
 pub struct Vector {
     /// X co-ordinate in 3D space.
@@ -77,3 +78,42 @@ impl PartialEq for Vector {
         !self.eq(other)
     }
 }
+
+pub fn add(a: &Vector, b: &Vector) -> Vector {
+    Vector { x: a.x + b.x, y: a.y + b.y, z: a.z + b.z }
+}
+
+pub fn sub(a: &Vector, b: &Vector) -> Vector {
+    Vector { x: a.x - b.x, y: a.y - b.y, z: a.z - b.z }
+}
+
+pub fn scale(a: &Vector, factor: f32) -> Vector {
+    Vector { x: a.x * factor, y: a.y * factor, z: a.z * factor }
+}
+
+pub fn dot(a: &Vector, b: &Vector) -> f32 {
+    a.x * b.x + a.y * b.y + a.z * b.z
+}
+
+pub fn cross(a: &Vector, b: &Vector) -> Vector {
+    Vector {
+        x: a.y * b.z - a.z * b.y,
+        y: a.z * b.x - a.x * b.z,
+        z: a.x * b.y - a.y * b.x,
+    }
+}
+
+/// Returns the normal of a polygon (not normalized) with Newell's method, which also works for
+/// concave and slightly non planar polygons.
+pub fn polygon_normal(vertices: &[Vector]) -> Vector {
+    let mut normal = Vector::new();
+
+    for (pos, current) in vertices.iter().enumerate() {
+        let next = &vertices[(pos + 1) % vertices.len()];
+
+        normal.x += (current.y - next.y) * (current.z + next.z);
+        normal.y += (current.z - next.z) * (current.x + next.x);
+        normal.z += (current.x - next.x) * (current.y + next.y);
+    }
+    normal
+}
@@ -0,0 +1,308 @@
+/*
+* Rust-FMOD - Copyright (c) 2018 Gomez Guillaume.
+*
+* The Original software, FmodEx library, is provided by FIRELIGHT TECHNOLOGIES.
+*
+* This software is provided 'as-is', without any express or implied warranty.
+* In no event will the authors be held liable for any damages arising from
+* the use of this software.
+*
+* Permission is granted to anyone to use this software for any purpose,
+* including commercial applications, and to alter it and redistribute it
+* freely, subject to the following restrictions:
+*
+* 1. The origin of this software must not be misrepresented; you must not claim
+*    that you wrote the original software. If you use this software in a product,
+*    an acknowledgment in the product documentation would be appreciated but is
+*    not required.
+*
+* 2. Altered source versions must be plainly marked as such, and must not be
+*    misrepresented as being the original software.
+*
+* 3. This notice may not be removed or altered from any source distribution.
+*/
+
+use ffi;
+use channel::Channel;
+use sound::Sound;
+use vector;
+use std::collections::HashMap;
+
+#[derive(Clone, PartialEq, PartialOrd, Debug, Copy)]
+/// What a [`VoiceManager`](struct.VoiceManager.html) category does when a new voice is requested
+/// while its budget is already full.
+pub enum StealPolicy {
+    /// The new voice is refused.
+    Reject,
+    /// The voice which started first is stopped.
+    Oldest,
+    /// The voice with the lowest [`Channel::get_audibility`](struct.Channel.html#method.get_audibility)
+    /// is stopped.
+    Quietest,
+    /// The voice the farthest from the first listener is stopped.
+    Farthest,
+    /// The voice with the lowest priority (the highest
+    /// [`Channel::get_priority`](struct.Channel.html#method.get_priority) value) is stopped. If
+    /// every playing voice is more important than the new one, the new one is refused.
+    LowestPriority,
+}
+
+#[derive(Clone, PartialEq, Debug, Copy, Default)]
+/// Statistics of a [`VoiceManager`](struct.VoiceManager.html) category.
+pub struct VoiceStats {
+    /// Number of voices currently alive.
+    pub active  : usize,
+    /// Maximum number of voices which were alive at the same time.
+    pub peak    : usize,
+    /// Number of voices started.
+    pub played  : usize,
+    /// Number of voices stopped to make room for a new one.
+    pub stolen  : usize,
+    /// Number of voices refused because the budget was full.
+    pub rejected: usize,
+}
+
+struct Voice {
+    channel: Channel,
+    started: u64,
+}
+
+struct Category {
+    max_voices: usize,
+    policy: StealPolicy,
+    voices: Vec<Voice>,
+    stats: VoiceStats,
+}
+
+impl Category {
+    fn prune(&mut self) {
+        self.voices.retain(|v| v.channel.is_playing().unwrap_or(false));
+        self.stats.active = self.voices.len();
+    }
+
+    /// Returns the index of the voice to stop or `None` if the new voice has to be refused.
+    fn find_victim(&self, sound: &Sound) -> Option<usize> {
+        let mut victim = None;
+
+        match self.policy {
+            StealPolicy::Reject => {}
+            StealPolicy::Oldest => {
+                let mut oldest = u64::MAX;
+
+                for (pos, voice) in self.voices.iter().enumerate() {
+                    if voice.started < oldest {
+                        oldest = voice.started;
+                        victim = Some(pos);
+                    }
+                }
+            }
+            StealPolicy::Quietest => {
+                let mut quietest = f32::MAX;
+
+                for (pos, voice) in self.voices.iter().enumerate() {
+                    let audibility = voice.channel.get_audibility().unwrap_or(0f32);
+
+                    if audibility < quietest {
+                        quietest = audibility;
+                        victim = Some(pos);
+                    }
+                }
+            }
+            StealPolicy::Farthest => {
+                let listener = match sound.get_system_object() {
+                    Ok(s) => match s.get_3D_listener_attributes(0) {
+                        Ok((pos, _, _, _)) => pos,
+                        Err(_) => vector::Vector::new()
+                    },
+                    Err(_) => vector::Vector::new()
+                };
+                let mut farthest = -1f32;
+
+                for (pos, voice) in self.voices.iter().enumerate() {
+                    let distance = match voice.channel.get_3D_attributes() {
+                        Ok((p, _)) => distance_squared(&p, &listener),
+                        Err(_) => f32::MAX
+                    };
+
+                    if distance > farthest {
+                        farthest = distance;
+                        victim = Some(pos);
+                    }
+                }
+            }
+            StealPolicy::LowestPriority => {
+                let new_priority = match sound.get_defaults() {
+                    Ok((_, _, _, priority)) => priority,
+                    Err(_) => 128
+                };
+                let mut lowest = -1i32;
+
+                for (pos, voice) in self.voices.iter().enumerate() {
+                    let priority = voice.channel.get_priority().unwrap_or(256);
+
+                    if priority > lowest {
+                        lowest = priority;
+                        victim = Some(pos);
+                    }
+                }
+                if lowest < new_priority {
+                    victim = None;
+                }
+            }
+        }
+        victim
+    }
+}
+
+pub fn distance_squared(a: &vector::Vector, b: &vector::Vector) -> f32 {
+    let x = a.x - b.x;
+    let y = a.y - b.y;
+    let z = a.z - b.z;
+
+    x * x + y * y + z * z
+}
+
+/// Keeps track of the channels played in each category and enforces a maximum number of voices
+/// per category.
+///
+/// Voices which stopped on their own are forgotten when
+/// [`VoiceManager::update`](struct.VoiceManager.html#method.update) is called or when a new voice
+/// is requested in their category.
+pub struct VoiceManager {
+    categories: HashMap<String, Category>,
+    clock: u64,
+}
+
+impl Default for VoiceManager {
+    fn default() -> VoiceManager {
+        VoiceManager::new()
+    }
+}
+
+impl VoiceManager {
+    pub fn new() -> VoiceManager {
+        VoiceManager {
+            categories: HashMap::new(),
+            clock: 0,
+        }
+    }
+
+    /// Adds a category or replaces the budget of an existing one. Voices already tracked are
+    /// kept.
+    pub fn add_category(&mut self, name: &str, max_voices: usize, policy: StealPolicy) {
+        let category = self.categories.entry(name.to_owned()).or_insert(Category {
+            max_voices: max_voices,
+            policy: policy,
+            voices: Vec::new(),
+            stats: Default::default(),
+        });
+
+        category.max_voices = max_voices;
+        category.policy = policy;
+    }
+
+    /// Stops every voice of the category and forgets it.
+    pub fn remove_category(&mut self, name: &str) -> ::Status {
+        match self.categories.remove(name) {
+            Some(category) => {
+                for voice in category.voices.iter() {
+                    voice.channel.stop();
+                }
+                ::Status::Ok
+            }
+            None => ::Status::InvalidParam
+        }
+    }
+
+    /// Plays the sound if the category budget allows it, stealing a voice if needed.
+    ///
+    /// Returns `Err(Status::ChannelAlloc)` if the voice was refused and `Err(Status::InvalidParam)`
+    /// if the category doesn't exist.
+    pub fn play(&mut self, category: &str, sound: &Sound) -> Result<Channel, ::Status> {
+        let category = match self.categories.get_mut(category) {
+            Some(c) => c,
+            None => return Err(::Status::InvalidParam)
+        };
+
+        category.prune();
+        if category.max_voices == 0 {
+            category.stats.rejected += 1;
+            return Err(::Status::ChannelAlloc);
+        }
+        if category.voices.len() >= category.max_voices {
+            match category.find_victim(sound) {
+                Some(pos) => {
+                    let voice = category.voices.remove(pos);
+
+                    voice.channel.stop();
+                    category.stats.stolen += 1;
+                }
+                None => {
+                    category.stats.rejected += 1;
+                    return Err(::Status::ChannelAlloc);
+                }
+            }
+        }
+
+        let channel = sound.play()?;
+
+        self.clock += 1;
+        category.voices.push(Voice {
+            channel: ffi::FFI::wrap(ffi::FFI::unwrap(&channel)),
+            started: self.clock,
+        });
+        category.stats.played += 1;
+        category.stats.active = category.voices.len();
+        if category.stats.active > category.stats.peak {
+            category.stats.peak = category.stats.active;
+        }
+        Ok(channel)
+    }
+
+    /// Forgets the voices which are not playing anymore. Should be called alongside
+    /// [`Sys::update`](struct.Sys.html#method.update).
+    pub fn update(&mut self) {
+        for category in self.categories.values_mut() {
+            category.prune();
+        }
+    }
+
+    /// Stops every voice of the category.
+    pub fn stop_category(&mut self, name: &str) -> ::Status {
+        match self.categories.get_mut(name) {
+            Some(category) => {
+                for voice in category.voices.drain(..) {
+                    voice.channel.stop();
+                }
+                category.stats.active = 0;
+                ::Status::Ok
+            }
+            None => ::Status::InvalidParam
+        }
+    }
+
+    pub fn get_num_voices(&self, name: &str) -> Result<usize, ::Status> {
+        match self.categories.get(name) {
+            Some(category) => Ok(category.voices.len()),
+            None => Err(::Status::InvalidParam)
+        }
+    }
+
+    pub fn get_stats(&self, name: &str) -> Result<VoiceStats, ::Status> {
+        match self.categories.get(name) {
+            Some(category) => Ok(category.stats),
+            None => Err(::Status::InvalidParam)
+        }
+    }
+
+    /// Resets the played/stolen/rejected counters and the peak of every category.
+    pub fn reset_stats(&mut self) {
+        for category in self.categories.values_mut() {
+            category.stats = VoiceStats {
+                active: category.voices.len(),
+                peak: category.voices.len(),
+                .. Default::default()
+            };
+        }
+    }
+}